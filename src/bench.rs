@@ -0,0 +1,186 @@
+//! Results of the in-game benchmark and the settings they were run with.
+//!
+//! The benchmark writes a text report per run into `Settings/Benchmarks`: a frames per second
+//! section followed by a frame time section, each with a `Min, Max, Avg` row per pass.
+//! The settings of a run are the config that was in place when it finished, the newest of
+//! `system.xml`, its backups and the profiles modified before the report.
+
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use crate::error::{Error, Result};
+use crate::video_card;
+use crate::xml;
+
+pub const BENCHMARKS_DIR: &str = "Benchmarks";
+
+/// Report that couldn't be read and why.
+pub type Unreadable = (PathBuf, Error);
+
+/// Minimum, maximum and average of one measure over a pass.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Stats {
+    pub min: f64,
+    pub max: f64,
+    pub avg: f64,
+}
+
+/// One scene of the benchmark.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Pass {
+    pub name: String,
+    pub fps: Stats,
+    /// Frame times in milliseconds, when the report has them.
+    pub frame_time: Option<Stats>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Benchmark {
+    pub path: PathBuf,
+    pub modified: SystemTime,
+    pub passes: Vec<Pass>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Section {
+    None,
+    Fps,
+    FrameTime,
+}
+
+impl Benchmark {
+    pub fn read_file(path: &Path) -> Result<Self> {
+        let passes = parse(&xml::read_to_string(path)?)?;
+        let modified = std::fs::metadata(path)?.modified()?;
+        Ok(Self { path: path.to_path_buf(), modified, passes })
+    }
+
+    /// Average of the passes' average FPS.
+    pub fn avg_fps(&self) -> f64 {
+        self.passes.iter().map(|pass| pass.fps.avg).sum::<f64>() / self.passes.len() as f64
+    }
+
+    /// Lowest FPS of any pass.
+    pub fn min_fps(&self) -> f64 {
+        self.passes.iter().map(|pass| pass.fps.min).fold(f64::INFINITY, f64::min)
+    }
+
+    /// Config in place when the run finished, see [`settings_at`].
+    pub fn settings_path(&self, configs: &[(PathBuf, SystemTime)]) -> Option<PathBuf> {
+        settings_at(self.modified, configs)
+    }
+}
+
+/// Passes of a report, lines outside the two sections are skipped.
+pub fn parse(content: &str) -> Result<Vec<Pass>> {
+    let mut passes: Vec<Pass> = vec![];
+    let mut section = Section::None;
+    let mut offset = 0;
+    for line in content.split_inclusive('\n') {
+        let line_offset = offset;
+        offset += line.len();
+        let trimmed = line.trim();
+        let lowercase = trimmed.to_lowercase();
+        if lowercase.starts_with("frames per second") {
+            section = Section::Fps;
+            continue;
+        }
+        if lowercase.starts_with("time in milliseconds") {
+            section = Section::FrameTime;
+            continue;
+        }
+        if trimmed.is_empty() || section == Section::None {
+            continue;
+        }
+        let fields: Vec<&str> = trimmed.split(',').map(str::trim).collect();
+        let [name, min, max, avg] = fields[..] else {
+            section = Section::None;
+            continue;
+        };
+        let number = |text: &str| text.parse::<f64>().map_err(|_| Error::parse_at(content, line_offset, &format!("'{text}' is not a number")));
+        let stats = Stats { min: number(min)?, max: number(max)?, avg: number(avg)? };
+        match section {
+            Section::Fps => passes.push(Pass { name: name.into(), fps: stats, frame_time: None }),
+            Section::FrameTime => {
+                if let Some(pass) = passes.iter_mut().find(|pass| pass.name == name) {
+                    pass.frame_time = Some(stats);
+                }
+            }
+            Section::None => {}
+        }
+    }
+    if passes.is_empty() {
+        return Err(Error::parse_at(content, 0, "no frames per second results found"));
+    }
+    Ok(passes)
+}
+
+/// `Settings/Benchmarks` next to `system.xml`.
+pub fn benchmarks_dir() -> Option<PathBuf> {
+    video_card::settings_dir().map(|dir| dir.join(BENCHMARKS_DIR))
+}
+
+/// Every report in the directory, oldest first, with the ones that couldn't be read.
+pub fn read_dir(dir: &Path) -> Result<(Vec<Benchmark>, Vec<Unreadable>)> {
+    let mut benchmarks = vec![];
+    let mut broken = vec![];
+    for entry in std::fs::read_dir(dir)?.flatten() {
+        let path = entry.path();
+        if path.extension().is_none_or(|ext| ext != "txt") {
+            continue;
+        }
+        match Benchmark::read_file(&path) {
+            Ok(benchmark) => benchmarks.push(benchmark),
+            Err(err) => broken.push((path, err)),
+        }
+    }
+    benchmarks.sort_by_key(|benchmark| benchmark.modified);
+    Ok((benchmarks, broken))
+}
+
+/// `system.xml`, its backups and the profiles with their modification time.
+pub fn config_files(settings_dir: &Path, profiles_dir: &Path) -> Vec<(PathBuf, SystemTime)> {
+    let mut configs = vec![];
+    for dir in [settings_dir, profiles_dir] {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let is_config = path.extension().is_some_and(|ext| ext == "xml" || ext == "bak");
+            if let (true, Ok(modified)) = (is_config, entry.metadata().and_then(|metadata| metadata.modified())) {
+                configs.push((path, modified));
+            }
+        }
+    }
+    configs
+}
+
+/// Newest config modified at or before `time`.
+pub fn settings_at(time: SystemTime, configs: &[(PathBuf, SystemTime)]) -> Option<PathBuf> {
+    configs.iter()
+        .filter(|(_, modified)| *modified <= time)
+        .max_by_key(|(_, modified)| *modified)
+        .map(|(path, _)| path.clone())
+}
+
+/// `2024-05-01 18:30` in UTC.
+pub fn format_time(time: SystemTime) -> String {
+    let seconds = time.duration_since(UNIX_EPOCH).map_or(0, |duration| duration.as_secs());
+    let (year, month, day) = civil_from_days((seconds / 86_400) as i64);
+    let minutes = seconds % 86_400 / 60;
+    format!("{year:04}-{month:02}-{day:02} {:02}:{:02}", minutes / 60, minutes % 60)
+}
+
+// Days since 1970-01-01 to a proleptic Gregorian date, Howard Hinnant's algorithm
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
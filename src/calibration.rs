@@ -0,0 +1,412 @@
+//! Calibration of the VRAM model from readings taken in-game.
+//!
+//! A reading is the settings the game ran with and the VRAM it reported. Readings are kept one
+//! per line in a local dataset, least squares over them gives new step costs, `MIN_VRAM` and the
+//! per-pixel factor, which are written to a catalog override replacing the built-in numbers:
+//!
+//! ```toml
+//! min_vram = 1652
+//! pixels_per_mb = 5090.4
+//!
+//! [textureQuality]
+//! kSettingLevel_Medium = 170
+//! kSettingLevel_High = 190
+//! ```
+//!
+//! Each step is the cost of going from the option before it to the named option.
+
+use std::fmt::Write;
+use std::io::Write as _;
+use std::path::Path;
+use crate::error::{Error, Result};
+use crate::export::{self, toml_key};
+use crate::settings::{self, Setting};
+use crate::settings::SettingType::Level;
+use crate::validation;
+use crate::vram;
+use crate::xml::{self, Element, Value};
+
+pub const DATASET_FILE: &str = "calibration/readings.txt";
+pub const OVERRIDE_FILE: &str = "calibration/catalog.toml";
+
+const MIN_VRAM_KEY: &str = "min_vram";
+const PIXELS_PER_MB_KEY: &str = "pixels_per_mb";
+const RIDGE: f64 = 1e-3;
+
+/// Numbers of the VRAM model, the built-in ones or fitted from readings.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Calibration {
+    pub min_vram: f64,
+    pub pixels_per_mb: f64,
+    /// `VRamLevels` of the settings that differ from the catalog.
+    pub steps: Vec<(String, Vec<usize>)>,
+}
+
+impl Calibration {
+    pub fn builtin() -> Self {
+        Self { min_vram: vram::MIN_VRAM as f64, pixels_per_mb: vram::PIXELS_PER_1MB_VRAM, steps: vec![] }
+    }
+
+    pub fn read_file(path: &Path) -> Result<Self> {
+        Self::parse(&xml::read_to_string(path)?)
+    }
+
+    /// Reads an override, settings and options left out keep their built-in costs.
+    pub fn parse(content: &str) -> Result<Self> {
+        let state = export::parse_toml(content)?;
+        let mut calibration = Self::builtin();
+        for (key, value) in &state.top {
+            let number = parse_cost(key, value)?;
+            match key.as_str() {
+                MIN_VRAM_KEY => calibration.min_vram = number,
+                PIXELS_PER_MB_KEY if number > 0.0 => calibration.pixels_per_mb = number,
+                PIXELS_PER_MB_KEY => return Err(invalid(&format!("{key} must be positive"))),
+                _ => return Err(invalid(&format!("unknown key '{key}'"))),
+            }
+        }
+        let catalog = settings::get_settings();
+        for (tag, table) in &state.sections {
+            let index = settings::get_setting_index_by_tag(&catalog, tag)?;
+            let Level(_, selectables, vram_levels) = &catalog[index].setting_type else {
+                return Err(invalid(&format!("{tag} has no level costs")));
+            };
+            let mut steps = vram_levels.clone();
+            for (option, value) in table {
+                let position = selectables.iter().skip(1).position(|selectable| &selectable.config_name == option);
+                let Some(position) = position.filter(|position| *position < steps.len()) else {
+                    return Err(invalid(&format!("{tag} has no step to {option}")));
+                };
+                steps[position] = parse_cost(option, value)?.round().max(0.0) as usize;
+            }
+            calibration.steps.push((tag.clone(), steps));
+        }
+        Ok(calibration)
+    }
+
+    /// Override file with every level cost of the catalog, under a `#` comment.
+    pub fn to_toml(&self, comment: &str) -> String {
+        let mut output = String::new();
+        for line in comment.lines() {
+            let _ = writeln!(output, "# {line}");
+        }
+        let _ = writeln!(output, "{MIN_VRAM_KEY} = {:.0}", self.min_vram);
+        let _ = writeln!(output, "{PIXELS_PER_MB_KEY} = {:.1}", self.pixels_per_mb);
+        let mut catalog = settings::get_settings();
+        self.apply(&mut catalog);
+        for setting in &catalog {
+            let Level(_, selectables, vram_levels) = &setting.setting_type else {
+                continue;
+            };
+            if vram_levels.is_empty() {
+                continue;
+            }
+            let _ = writeln!(output, "\n[{}]", toml_key(&setting.tag));
+            for (selectable, cost) in selectables.iter().skip(1).zip(vram_levels) {
+                let _ = writeln!(output, "{} = {cost}", toml_key(&selectable.config_name));
+            }
+        }
+        output
+    }
+
+    /// Estimated VRAM usage in MB with these numbers, whatever level costs the settings carry.
+    pub fn estimate(&self, settings: &[Setting]) -> f64 {
+        let mut calibrated = settings.to_vec();
+        self.apply(&mut calibrated);
+        vram::estimate_with(&calibrated, self)
+    }
+
+    /// Replaces the level costs of the settings.
+    pub fn apply(&self, settings: &mut [Setting]) {
+        for (tag, steps) in &self.steps {
+            let Some(setting) = settings.iter_mut().find(|setting| &setting.tag == tag) else {
+                continue;
+            };
+            if let Level(_, _, vram_levels) = &mut setting.setting_type {
+                vram_levels.clone_from(steps);
+            }
+        }
+    }
+}
+
+fn invalid(message: &str) -> Error {
+    Error::Validation(format!("calibration override: {message}"))
+}
+
+fn parse_cost(key: &str, value: &str) -> Result<f64> {
+    value.parse().map_err(|_| invalid(&format!("{key} expects a number, got '{value}'")))
+}
+
+/// Settings the game ran with and the VRAM it reported.
+#[derive(Clone)]
+pub struct Reading {
+    pub measured: f64,
+    pub settings: Vec<Setting>,
+}
+
+impl Reading {
+    // Render target costs aren't fitted, they're taken out of the measurement as they are
+    fn scaled_vram(&self) -> f64 {
+        let megapixels = vram::render_megapixels(&self.settings);
+        self.settings.iter().map(|setting| vram::scaled_vram(setting, megapixels)).sum()
+    }
+}
+
+/// Reading as a dataset line: the MB followed by `tag=value` for every setting.
+pub fn reading_line(measured: f64, settings: &[Setting]) -> String {
+    let mut line = format!("{measured}");
+    for setting in settings {
+        let _ = write!(line, " {}={}", setting.tag, xml::setting_text(setting));
+    }
+    line
+}
+
+/// Appends a reading to the dataset, creating it if needed.
+pub fn record(path: &Path, measured: f64, settings: &[Setting]) -> Result<()> {
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        std::fs::create_dir_all(dir)?;
+    }
+    let mut file = std::fs::OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", reading_line(measured, settings))?;
+    Ok(())
+}
+
+/// Reads every reading, `#` starts a comment and settings left out keep their defaults.
+pub fn read_dataset(path: &Path) -> Result<Vec<Reading>> {
+    parse_dataset(&xml::read_to_string(path)?)
+}
+
+pub fn parse_dataset(content: &str) -> Result<Vec<Reading>> {
+    let mut readings = vec![];
+    let mut offset = 0;
+    for line in content.split_inclusive('\n') {
+        let line_offset = offset;
+        offset += line.len();
+        let error = |message: &str| Error::parse_at(content, line_offset, message);
+        let mut words = line.split('#').next().unwrap_or("").split_whitespace();
+        let Some(first) = words.next() else {
+            continue;
+        };
+        let measured: f64 = first.parse().map_err(|_| error(&format!("expected the measured MB, got '{first}'")))?;
+        let mut reading_settings = settings::get_settings();
+        for word in words {
+            let Some((tag, value)) = word.split_once('=') else {
+                return Err(error(&format!("expected tag=value, got '{word}'")));
+            };
+            let index = settings::get_setting_index_by_tag(&reading_settings, tag).map_err(|err| error(&err.to_string()))?;
+            let setting = &mut reading_settings[index];
+            let element = Element::new(Some(setting.section), tag, Value::Attribute(value.into()));
+            xml::read_setting(setting, &element).map_err(|_| error(&format!("invalid value '{value}' for {tag}")))?;
+        }
+        if let Some(issue) = validation::check_settings(&reading_settings).into_iter().next() {
+            return Err(error(&format!("{}: {}", issue.tag, issue.message)));
+        }
+        readings.push(Reading { measured, settings: reading_settings });
+    }
+    Ok(readings)
+}
+
+/// Fitted calibration together with how well it explains the readings.
+pub struct Fit {
+    pub calibration: Calibration,
+    /// Measured and predicted MB of every reading.
+    pub residuals: Vec<(f64, f64)>,
+    pub rmse: f64,
+    pub max_error: f64,
+    /// `None` when every reading measured the same.
+    pub r_squared: Option<f64>,
+    /// Parameters that didn't change across the readings and kept their prior value.
+    pub unconstrained: Vec<String>,
+    /// Name, prior and fitted value of every parameter that moved by at least 1 MB.
+    pub changes: Vec<(String, f64, f64)>,
+}
+
+struct Parameter {
+    name: String,
+    prior: f64,
+    // Setting index and step for level costs
+    step: Option<(usize, usize)>,
+}
+
+/// Least squares fit of `MIN_VRAM`, MB per megapixel and the fixed part of every level step, starting from `prior`.
+pub fn fit(readings: &[Reading], prior: &Calibration) -> Result<Fit> {
+    if readings.len() < 2 {
+        return Err(Error::Validation("at least 2 readings are needed to calibrate".into()));
+    }
+    let mut catalog = settings::get_settings();
+    prior.apply(&mut catalog);
+    let mut parameters = vec![
+        Parameter { name: "MIN_VRAM".into(), prior: prior.min_vram, step: None },
+        Parameter { name: "MB per megapixel".into(), prior: 1e6 / prior.pixels_per_mb, step: None },
+    ];
+    for (index, setting) in catalog.iter().enumerate() {
+        if let Level(_, selectables, vram_levels) = &setting.setting_type {
+            for (step, (cost, selectable)) in vram_levels.iter().zip(selectables.iter().skip(1)).enumerate() {
+                let name = format!("{} {}", setting.tag, selectable.nice_name);
+                parameters.push(Parameter { name, prior: *cost as f64, step: Some((index, step)) });
+            }
+        }
+    }
+
+    let rows: Vec<Vec<f64>> = readings.iter().map(|reading| features(&parameters, &reading.settings)).collect();
+    // Parameters the readings don't vary are kept as they are, so the base cost doesn't leak into them
+    let mut free: Vec<bool> = (0..parameters.len())
+        .map(|i| i == 0 || rows.iter().any(|row| row[i] != rows[0][i]))
+        .collect();
+    let mut solution: Vec<f64> = parameters.iter().map(|parameter| parameter.prior).collect();
+    loop {
+        let fitted = solve_free(&rows, readings, &free, &solution)?;
+        for (i, value) in fitted {
+            solution[i] = value;
+        }
+        // A negative step cost is noise, pin it at 0 and fit the rest again
+        let negative: Vec<usize> = (2..parameters.len()).filter(|i| free[*i] && solution[*i] < 0.0).collect();
+        if negative.is_empty() {
+            break;
+        }
+        for i in negative {
+            free[i] = false;
+            solution[i] = 0.0;
+        }
+    }
+
+    let mb_per_megapixel = solution[1];
+    if mb_per_megapixel <= 0.0 {
+        return Err(Error::Validation("the fitted resolution cost isn't positive, add readings at different resolutions".into()));
+    }
+    let mut calibration = Calibration { min_vram: solution[0], pixels_per_mb: 1e6 / mb_per_megapixel, steps: vec![] };
+    for (index, setting) in catalog.iter().enumerate() {
+        let Level(_, _, vram_levels) = &setting.setting_type else {
+            continue;
+        };
+        if vram_levels.is_empty() {
+            continue;
+        }
+        let steps = parameters.iter().zip(&solution)
+            .filter(|(parameter, _)| parameter.step.is_some_and(|(setting_index, _)| setting_index == index))
+            .map(|(_, value)| value.round().max(0.0) as usize)
+            .collect();
+        calibration.steps.push((setting.tag.clone(), steps));
+    }
+
+    let unconstrained = parameters.iter().enumerate().skip(1)
+        .filter(|(i, _)| rows.iter().all(|row| row[*i] == rows[0][*i]))
+        .map(|(_, parameter)| parameter.name.clone())
+        .collect();
+    let changes = parameters.iter().zip(&solution)
+        .filter(|(parameter, value)| (parameter.prior - **value).abs() >= 1.0)
+        .map(|(parameter, value)| (parameter.name.clone(), parameter.prior, *value))
+        .collect();
+
+    // Judged with the rounded numbers that get written
+    let residuals: Vec<(f64, f64)> = readings.iter().map(|reading| {
+        let mut fitted = reading.settings.clone();
+        calibration.apply(&mut fitted);
+        (reading.measured, vram::estimate_with(&fitted, &calibration))
+    }).collect();
+    let squared: f64 = residuals.iter().map(|(measured, predicted)| (measured - predicted).powi(2)).sum();
+    let rmse = (squared / residuals.len() as f64).sqrt();
+    let max_error = residuals.iter().map(|(measured, predicted)| (measured - predicted).abs()).fold(0.0, f64::max);
+    let mean = readings.iter().map(|reading| reading.measured).sum::<f64>() / readings.len() as f64;
+    let total: f64 = readings.iter().map(|reading| (reading.measured - mean).powi(2)).sum();
+    let r_squared = (total > 0.0).then(|| 1.0 - squared / total);
+
+    Ok(Fit { calibration, residuals, rmse, max_error, r_squared, unconstrained, changes })
+}
+
+// Constant, megapixels above the baseline, then 1 for every step the selected level includes
+fn features(parameters: &[Parameter], reading: &[Setting]) -> Vec<f64> {
+    let (width, height) = vram::resolution(reading);
+    let pixels = (width * height) as f64 - (vram::BASE_WIDTH * vram::BASE_HEIGHT) as f64;
+    let mut row = vec![1.0, pixels / 1e6];
+    for parameter in &parameters[2..] {
+        let Some((index, step)) = parameter.step else {
+            continue;
+        };
+        let included = match &reading[index].setting_type {
+            Level(selected_index, _, _) => step < *selected_index,
+            _ => false,
+        };
+        row.push(if included { 1.0 } else { 0.0 });
+    }
+    row
+}
+
+// Least squares of the free parameters with the fixed ones subtracted from the readings,
+// a small ridge towards the current values settles parameters that always change together:
+// (AᵀA + λI) x = Aᵀb + λ x₀
+fn solve_free(rows: &[Vec<f64>], readings: &[Reading], free: &[bool], current: &[f64]) -> Result<Vec<(usize, f64)>> {
+    let indices: Vec<usize> = (0..free.len()).filter(|i| free[*i]).collect();
+    let size = indices.len();
+    let mut matrix = vec![vec![0.0; size + 1]; size];
+    for (row, reading) in rows.iter().zip(readings) {
+        let fixed: f64 = (0..free.len()).filter(|i| !free[*i]).map(|i| row[i] * current[i]).sum();
+        for (a, &i) in indices.iter().enumerate() {
+            for (b, &j) in indices.iter().enumerate() {
+                matrix[a][b] += row[i] * row[j];
+            }
+            matrix[a][size] += row[i] * (reading.measured - fixed - reading.scaled_vram());
+        }
+    }
+    for (a, &i) in indices.iter().enumerate() {
+        matrix[a][a] += RIDGE;
+        matrix[a][size] += RIDGE * current[i];
+    }
+    Ok(indices.into_iter().zip(solve(matrix)?).collect())
+}
+
+// Gaussian elimination with partial pivoting on an augmented matrix
+pub(crate) fn solve(mut matrix: Vec<Vec<f64>>) -> Result<Vec<f64>> {
+    let size = matrix.len();
+    for column in 0..size {
+        let pivot = (column..size)
+            .max_by(|a, b| matrix[*a][column].abs().total_cmp(&matrix[*b][column].abs()))
+            .unwrap_or(column);
+        if matrix[pivot][column].abs() < 1e-12 {
+            return Err(Error::Validation("the readings can't be fitted".into()));
+        }
+        matrix.swap(column, pivot);
+        let (upper, lower) = matrix.split_at_mut(column + 1);
+        let pivot_row = &upper[column];
+        for row in lower {
+            let factor = row[column] / pivot_row[column];
+            if factor == 0.0 {
+                continue;
+            }
+            for (value, pivot_value) in row[column..].iter_mut().zip(&pivot_row[column..]) {
+                *value -= factor * pivot_value;
+            }
+        }
+    }
+    let mut solution = vec![0.0; size];
+    for row in (0..size).rev() {
+        let known: f64 = (row + 1..size).map(|k| matrix[row][k] * solution[k]).sum();
+        solution[row] = (matrix[row][size] - known) / matrix[row][row];
+    }
+    Ok(solution)
+}
+
+/// Plain-text fit quality report.
+pub fn format_report(fit: &Fit) -> String {
+    let mut output = String::new();
+    let r_squared = fit.r_squared.map_or("n/a".to_string(), |r_squared| format!("{r_squared:.3}"));
+    let _ = writeln!(output, "Readings: {}", fit.residuals.len());
+    let _ = writeln!(output, "RMSE: {:.1} MB, largest error: {:.1} MB, R²: {r_squared}", fit.rmse, fit.max_error);
+    if !fit.changes.is_empty() {
+        let _ = writeln!(output, "\nChanged:");
+        for (name, before, after) in &fit.changes {
+            let _ = writeln!(output, "  {name:<48} {before:>8.1} -> {after:>8.1}");
+        }
+    }
+    if !fit.unconstrained.is_empty() {
+        let _ = writeln!(output, "\nNot varied in the readings, kept at their current value:");
+        for name in &fit.unconstrained {
+            let _ = writeln!(output, "  {name}");
+        }
+    }
+    let _ = writeln!(output, "\nResiduals:");
+    for (i, (measured, predicted)) in fit.residuals.iter().enumerate() {
+        let _ = writeln!(output, "  #{:<4} measured {measured:>7.0} MB  predicted {predicted:>7.0} MB  error {:>+6.0} MB",
+            i + 1, predicted - measured);
+    }
+    output
+}
@@ -0,0 +1,471 @@
+//! Typed builder over the settings catalog.
+//!
+//! ```
+//! use rdr2_gfx_selector::{Level, SystemConfig};
+//!
+//! let xml = SystemConfig::new()
+//!     .texture_quality(Level::Ultra)
+//!     .msaa(4)
+//!     .resolution(2560, 1440)
+//!     .video_card("NVIDIA GeForce RTX 3070")
+//!     .to_xml()
+//!     .unwrap();
+//! assert!(xml.contains("<msaa value=\"4\"/>"));
+//! ```
+//!
+//! Setters never fail, the first problem is reported by [`SystemConfig::to_xml`]
+//! or [`SystemConfig::write_file`] so calls can be chained.
+
+use std::borrow::Cow;
+use std::path::Path;
+use crate::error::{Error, Result};
+use crate::settings::{self, Setting, SettingType, XMLSection};
+use crate::resolution;
+use crate::validation;
+use crate::xml::{self, Document, Element, Value};
+
+/// Quality level of `kSettingLevel_*` settings.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Level {
+    Low,
+    Medium,
+    High,
+    Ultra,
+}
+
+impl Level {
+    pub fn config_name(&self) -> &'static str {
+        match self {
+            Level::Low => "kSettingLevel_Low",
+            Level::Medium => "kSettingLevel_Medium",
+            Level::High => "kSettingLevel_High",
+            Level::Ultra => "kSettingLevel_Ultra",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Api {
+    Vulkan,
+    DirectX12,
+}
+
+impl Api {
+    pub fn config_name(&self) -> &'static str {
+        match self {
+            Api::Vulkan => settings::VULKAN,
+            Api::DirectX12 => settings::DX12,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum VSync {
+    Off = 0,
+    On = 1,
+    Half = 2,
+}
+
+/// Value of `dlssQuality`, the NGX quality mode.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum DlssQuality {
+    Performance = 0,
+    Balanced = 1,
+    Quality = 2,
+    UltraPerformance = 3,
+    UltraQuality = 4,
+    Dlaa = 5,
+}
+
+/// Value of `fsrQuality`.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum FsrQuality {
+    UltraQuality = 0,
+    Quality = 1,
+    Balanced = 2,
+    Performance = 3,
+}
+
+/// Value of `windowed`.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ScreenType {
+    Fullscreen = 0,
+    Windowed = 1,
+    Borderless = 2,
+}
+
+/// Catalog settings, hidden default-section values and the video card description,
+/// everything needed to write `system.xml`.
+#[derive(Clone)]
+pub struct SystemConfig {
+    settings: Vec<Setting>,
+    defaults: Vec<Element>,
+    video_card: Option<String>,
+    // Rejected setter calls, reported when writing
+    errors: Vec<String>,
+    // Resolution and refresh rate the mode indexes in `defaults` belong to
+    mode: DisplayMode,
+}
+
+type DisplayMode = ((usize, usize), Option<(usize, usize)>);
+
+impl Default for SystemConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SystemConfig {
+    /// Every setting at its default value and no video card.
+    pub fn new() -> Self {
+        let settings = settings::get_settings();
+        Self {
+            mode: display_mode(&settings),
+            settings,
+            defaults: xml::default_elements(),
+            video_card: None,
+            errors: vec![],
+        }
+    }
+
+    /// Starts from an existing `system.xml`, settings missing from it keep their defaults.
+    pub fn read_file(path: &Path) -> Result<Self> {
+        let document = Document::read_file(path)?;
+        Self::from_document(&document)
+    }
+
+    pub fn from_document(document: &Document) -> Result<Self> {
+        let mut config = Self::new();
+        document.apply_to(&mut config.settings)?;
+        document.apply_to_defaults(&mut config.defaults);
+        config.video_card = document.video_card().map(String::from);
+        config.keep_mode_indexes();
+        Ok(config)
+    }
+
+    /// Takes the mode indexes in the hidden values as the ones of the current resolution and refresh rate.
+    pub(crate) fn keep_mode_indexes(&mut self) {
+        self.mode = display_mode(&self.settings);
+    }
+
+    pub fn settings(&self) -> &[Setting] {
+        &self.settings
+    }
+
+    pub fn settings_mut(&mut self) -> &mut [Setting] {
+        &mut self.settings
+    }
+
+    /// Elements outside the catalog, written with their values as they are.
+    pub fn defaults(&self) -> &[Element] {
+        &self.defaults
+    }
+
+    /// Hidden values as they're written. The mode list indexes stay as they were read
+    /// until the resolution or refresh rate changes, then they go back to their defaults.
+    pub fn written_defaults(&self) -> Cow<'_, [Element]> {
+        let (resolution, rate) = display_mode(&self.settings);
+        let mut reset: Vec<&str> = vec![];
+        if resolution != self.mode.0 {
+            reset.extend(xml::RESOLUTION_INDEX_TAGS);
+        }
+        if rate != self.mode.1 {
+            reset.push(xml::REFRESH_RATE_INDEX_TAG);
+        }
+        if reset.is_empty() {
+            return Cow::Borrowed(&self.defaults);
+        }
+        let builtin = xml::default_elements();
+        let mut defaults = self.defaults.clone();
+        for element in defaults.iter_mut().filter(|element| element.section == Some(XMLSection::Video) && reset.contains(&element.tag.as_str())) {
+            if let Some(default) = builtin.iter().find(|default| default.section == element.section && default.tag == element.tag) {
+                element.value = default.value.clone();
+            }
+        }
+        Cow::Owned(defaults)
+    }
+
+    /// Changes a hidden value, the kind of value (attribute or text) is kept.
+    pub fn set_default(&mut self, section: XMLSection, tag: &str, value: &str) -> Result<()> {
+        let Some(element) = self.defaults.iter_mut().find(|element| element.section == Some(section) && element.tag == tag) else {
+            return Err(Error::MissingSetting(format!("{}.{tag}", section.tag())));
+        };
+        element.value = match element.value {
+            Value::Text(_) => Value::Text(value.into()),
+            _ => Value::Attribute(value.into()),
+        };
+        Ok(())
+    }
+
+    /// Sets a catalog setting or a hidden value by tag, taking the value as it's written in the file.
+    pub fn set_value(&mut self, tag: &str, value: &str) -> Result<()> {
+        if let Ok(setting) = self.setting_mut(tag) {
+            let mut candidate = setting.clone();
+            let element = Element::new(Some(candidate.section), tag, Value::Attribute(value.into()));
+            xml::read_setting(&mut candidate, &element).map_err(|_| Error::Validation(format!("invalid value '{value}' for {tag}")))?;
+            validation::validate(std::slice::from_ref(&candidate))?;
+            *setting = candidate;
+            return Ok(());
+        }
+        let Some(section) = self.defaults.iter().find(|element| element.tag == tag).and_then(|element| element.section) else {
+            return Err(Error::MissingSetting(tag.into()));
+        };
+        self.set_default(section, tag, value)
+    }
+
+    pub fn setting(&self, tag: &str) -> Result<&Setting> {
+        let index = settings::get_setting_index_by_tag(&self.settings, tag)?;
+        Ok(&self.settings[index])
+    }
+
+    pub fn setting_mut(&mut self, tag: &str) -> Result<&mut Setting> {
+        let index = settings::get_setting_index_by_tag(&self.settings, tag)?;
+        Ok(&mut self.settings[index])
+    }
+
+    pub fn video_card_name(&self) -> Option<&str> {
+        self.video_card.as_deref()
+    }
+
+    pub fn video_card(mut self, name: &str) -> Self {
+        let trimmed = name.trim();
+        if trimmed.is_empty() {
+            self.errors.push("video card name can't be empty".into());
+        } else {
+            self.video_card = Some(trimmed.to_string());
+        }
+        self
+    }
+
+    /// Sets any `kSettingLevel_*` setting by tag.
+    pub fn level(self, tag: &str, level: Level) -> Self {
+        self.option(tag, level.config_name())
+    }
+
+    /// Sets any on/off setting by tag.
+    pub fn toggle(mut self, tag: &str, enabled: bool) -> Self {
+        let result = self.setting_mut(tag).map(|setting| match &mut setting.setting_type {
+            SettingType::OnOff(value) => {
+                *value = enabled;
+                Ok(())
+            }
+            _ => Err(format!("{tag} is not an on/off setting")),
+        });
+        self.record(result);
+        self
+    }
+
+    pub fn texture_quality(self, level: Level) -> Self {
+        self.level("textureQuality", level)
+    }
+
+    pub fn lighting_quality(self, level: Level) -> Self {
+        self.level("lightingQuality", level)
+    }
+
+    pub fn shadow_quality(self, level: Level) -> Self {
+        self.level("shadowQuality", level)
+    }
+
+    pub fn reflection_quality(self, level: Level) -> Self {
+        self.level("reflectionQuality", level)
+    }
+
+    pub fn volumetrics_quality(self, level: Level) -> Self {
+        self.level("volumetricsQuality", level)
+    }
+
+    pub fn water_quality(self, level: Level) -> Self {
+        self.level("waterQuality", level)
+    }
+
+    pub fn taa(self, level: Level) -> Self {
+        self.level("taa", level)
+    }
+
+    pub fn fxaa(self, enabled: bool) -> Self {
+        self.toggle("fxaaEnabled", enabled)
+    }
+
+    pub fn hdr(self, enabled: bool) -> Self {
+        self.toggle("hdr", enabled)
+    }
+
+    /// Also sets the companion toggles recommended for the API, set them afterwards to override.
+    pub fn api(self, api: Api) -> Self {
+        let mut config = self.option("API", api.config_name());
+        settings::apply_api_defaults(&mut config.settings);
+        config
+    }
+
+    /// 0 turns MSAA off, otherwise 2, 4 or 8.
+    pub fn msaa(self, factor: usize) -> Self {
+        self.multiplier("msaa", factor)
+    }
+
+    /// 0 turns it off, otherwise 2, 4, 8 or 16.
+    pub fn anisotropic_filtering(self, factor: usize) -> Self {
+        self.multiplier("anisotropicFiltering", factor)
+    }
+
+    pub fn vsync(mut self, vsync: VSync) -> Self {
+        let result = self.setting_mut("vSync").map(|setting| match &mut setting.setting_type {
+            SettingType::OnHalfOff(value) => {
+                *value = vsync as usize;
+                Ok(())
+            }
+            _ => Err("vSync is not an off/on/half setting".to_string()),
+        });
+        self.record(result);
+        self
+    }
+
+    pub fn screen_type(self, screen_type: ScreenType) -> Self {
+        self.number("windowed", screen_type as usize)
+    }
+
+    /// 0 is the first GPU, up to 3.
+    pub fn adapter(self, index: usize) -> Self {
+        self.number("adapterIndex", index)
+    }
+
+    /// 0 is the first monitor, up to 3.
+    pub fn output(self, index: usize) -> Self {
+        self.number("outputIndex", index)
+    }
+
+    /// Turns DLSS on with the quality mode, `None` turns it off.
+    pub fn dlss(self, quality: Option<DlssQuality>) -> Self {
+        match quality {
+            Some(quality) => self.number("dlssIndex", 1).number("dlssQuality", quality as usize),
+            None => self.number("dlssIndex", 0),
+        }
+    }
+
+    /// Turns FSR on with the quality mode, `None` turns it off.
+    pub fn fsr(self, quality: Option<FsrQuality>) -> Self {
+        match quality {
+            Some(quality) => self.toggle("fsrEnabled", true).number("fsrQuality", quality as usize),
+            None => self.toggle("fsrEnabled", false),
+        }
+    }
+
+    /// `resolution_scale(2, 3)` renders at 2/3 of the width and height, `(1, 1)` turns it off.
+    pub fn resolution_scale(self, numerator: usize, denominator: usize) -> Self {
+        self.option("scalingMode", &format!("kSettingScale_Mode{numerator}o{denominator}"))
+    }
+
+    /// `refresh_rate(144, 1)`, or `refresh_rate(60000, 1001)` for the 59.94 Hz some monitors report.
+    pub fn refresh_rate(mut self, numerator: usize, denominator: usize) -> Self {
+        let text = format!("{numerator}/{denominator}");
+        let result = self.setting_mut("refreshRateNumerator").and_then(|setting| validation::set_typed(setting, &text));
+        self.record_error(result);
+        self
+    }
+
+    pub fn resolution(mut self, width: usize, height: usize) -> Self {
+        let result = resolution::set_resolution(&mut self.settings, width, height);
+        self.record_error(result);
+        self
+    }
+
+    /// Sets a standard mode or `WIDTHxHEIGHT`, see [`resolution::parse`].
+    pub fn resolution_named(mut self, text: &str) -> Self {
+        match resolution::parse(text) {
+            Ok((width, height)) => self.resolution(width, height),
+            Err(err) => {
+                self.record_error(Err(err));
+                self
+            }
+        }
+    }
+
+    /// Width and height in pixels.
+    pub fn screen_resolution(&self) -> (usize, usize) {
+        crate::vram::resolution(&self.settings)
+    }
+
+    /// First rejected setter call or invalid setting, the video card isn't needed.
+    pub fn check_settings(&self) -> Result<()> {
+        if let Some(message) = self.errors.first() {
+            return Err(Error::Validation(message.clone()));
+        }
+        validation::validate(&self.settings)
+    }
+
+    /// First rejected setter call, then the first invalid setting or a missing video card.
+    pub fn check(&self) -> Result<&str> {
+        self.check_settings()?;
+        match &self.video_card {
+            Some(card_name) => Ok(card_name),
+            None => Err(Error::Validation("video card name is missing".into())),
+        }
+    }
+
+    pub fn to_xml(&self) -> Result<String> {
+        let card_name = self.check()?;
+        xml::to_xml_with_defaults(&self.settings, &self.written_defaults(), card_name)
+    }
+
+    pub fn write_file(&self, path: &Path) -> Result<()> {
+        let card_name = self.check()?;
+        xml::write_file(path, &self.settings, &self.written_defaults(), card_name)
+    }
+
+    /// Writes like [`write_file`](Self::write_file) and marks the file read-only so the game can't revert it.
+    pub fn write_file_read_only(&self, path: &Path) -> Result<()> {
+        self.write_file(path)?;
+        xml::set_read_only(path, true)
+    }
+
+    fn option(mut self, tag: &str, config_name: &str) -> Self {
+        let result = self.setting_mut(tag).map(|setting| match &mut setting.setting_type {
+            SettingType::Level(selected_index, selectables, _) => {
+                match selectables.iter().position(|option| option.config_name == config_name) {
+                    Some(position) => {
+                        *selected_index = position;
+                        Ok(())
+                    }
+                    None => Err(format!("{tag} has no {config_name} option")),
+                }
+            }
+            _ => Err(format!("{tag} is not a level setting")),
+        });
+        self.record(result);
+        self
+    }
+
+    fn multiplier(self, tag: &str, factor: usize) -> Self {
+        self.number(tag, factor)
+    }
+
+    fn number(mut self, tag: &str, value: usize) -> Self {
+        let result = self.setting_mut(tag).and_then(|setting| validation::set_number(setting, value));
+        self.record_error(result);
+        self
+    }
+
+    fn record_error(&mut self, result: Result<()>) {
+        match result {
+            Ok(()) => {}
+            Err(Error::Validation(message)) => self.errors.push(message),
+            Err(err) => self.errors.push(err.to_string()),
+        }
+    }
+
+    fn record(&mut self, result: Result<std::result::Result<(), String>>) {
+        match result {
+            Ok(Ok(())) => {}
+            Ok(Err(message)) => self.errors.push(message),
+            Err(err) => self.errors.push(err.to_string()),
+        }
+    }
+}
+
+fn display_mode(settings: &[Setting]) -> DisplayMode {
+    let rate = settings.iter().find_map(|setting| match setting.setting_type {
+        SettingType::RefreshRate(numerator, denominator) => Some((numerator, denominator)),
+        _ => None,
+    });
+    (crate::vram::resolution(settings), rate)
+}
@@ -0,0 +1,60 @@
+use std::fmt::{Display, Formatter};
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug)]
+pub enum Error {
+    Io(std::io::Error),
+    // 1-based position in the parsed document
+    Parse { line: usize, column: usize, message: String },
+    Validation(String),
+    MissingSetting(String),
+    Terminal(String),
+}
+
+impl Error {
+    pub fn parse_at(content: &str, offset: usize, message: &str) -> Self {
+        let (line, column) = line_and_column(content, offset);
+        Error::Parse { line, column, message: message.into() }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Io(err) => write!(f, "I/O error: {err}"),
+            Error::Parse { line, column, message } => write!(f, "Parse error at {line}:{column}: {message}"),
+            Error::Validation(message) => write!(f, "Invalid settings: {message}"),
+            Error::MissingSetting(tag) => write!(f, "Setting not found: {tag}"),
+            Error::Terminal(message) => write!(f, "Terminal error: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+// Offsets past the end or inside a multi-byte char are clamped to the preceding char
+pub(crate) fn line_and_column(content: &str, offset: usize) -> (usize, usize) {
+    let mut offset = offset.min(content.len());
+    while !content.is_char_boundary(offset) {
+        offset -= 1;
+    }
+    let before = &content[..offset];
+    let line = before.matches('\n').count() + 1;
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    let column = before[line_start..].chars().count() + 1;
+    (line, column)
+}
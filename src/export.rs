@@ -0,0 +1,418 @@
+//! JSON and TOML export of the full configuration state.
+//!
+//! Every section is a table of `tag = value` with values kept exactly as they're written
+//! to `system.xml`, keys are sorted so exports diff cleanly:
+//!
+//! ```toml
+//! version = "37"
+//! videoCardDescription = "NVIDIA GeForce RTX 3070"
+//!
+//! [graphics]
+//! msaa = "4"
+//! textureQuality = "kSettingLevel_Ultra"
+//! ```
+//!
+//! Importing an export gives back a config that writes the identical `system.xml`.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use crate::config::SystemConfig;
+use crate::error::{Error, Result};
+use crate::settings::XMLSection;
+use crate::validation;
+use crate::xml::{self, Element, Value};
+
+const VERSION_KEY: &str = "version";
+const VIDEO_CARD_KEY: &str = "videoCardDescription";
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Format {
+    Json,
+    Toml,
+}
+
+impl Format {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "json" => Some(Format::Json),
+            "toml" => Some(Format::Toml),
+            _ => None,
+        }
+    }
+
+    /// Guesses the format from a file extension.
+    pub fn from_path(path: &std::path::Path) -> Option<Self> {
+        Self::from_name(path.extension()?.to_str()?)
+    }
+}
+
+pub(crate) type Table = BTreeMap<String, String>;
+
+/// Sorted state: top-level values and one table per section.
+pub(crate) struct State {
+    pub(crate) top: Table,
+    pub(crate) sections: BTreeMap<String, Table>,
+}
+
+fn collect_state(config: &SystemConfig) -> State {
+    let mut top = Table::new();
+    top.insert(VERSION_KEY.into(), xml::VERSION.into());
+    if let Some(card_name) = config.video_card_name() {
+        top.insert(VIDEO_CARD_KEY.into(), card_name.into());
+    }
+    let mut sections: BTreeMap<String, Table> = BTreeMap::new();
+    for element in config.written_defaults().iter() {
+        if let Some(section) = element.section {
+            let table = sections.entry(section.tag().into()).or_default();
+            table.insert(element.tag.clone(), element.value.as_str().into());
+        }
+    }
+    for setting in config.settings() {
+        let table = sections.entry(setting.section.tag().into()).or_default();
+        table.insert(setting.tag.clone(), xml::setting_text(setting));
+    }
+    State { top, sections }
+}
+
+pub fn export(config: &SystemConfig, format: Format) -> String {
+    let state = collect_state(config);
+    match format {
+        Format::Json => to_json(&state),
+        Format::Toml => to_toml(&state),
+    }
+}
+
+/// Rebuilds the config, keys missing from the content keep their defaults.
+pub fn import(content: &str, format: Format) -> Result<SystemConfig> {
+    let state = match format {
+        Format::Json => JsonParser { content, position: 0 }.parse()?,
+        Format::Toml => parse_toml(content)?,
+    };
+    let mut config = SystemConfig::new();
+    for (key, value) in &state.top {
+        match key.as_str() {
+            VERSION_KEY if value != xml::VERSION => {
+                return Err(Error::Validation(format!("version is {value}, expected {}", xml::VERSION)));
+            }
+            VERSION_KEY => {}
+            VIDEO_CARD_KEY => config = config.video_card(value),
+            _ => return Err(Error::Validation(format!("unknown key '{key}'"))),
+        }
+    }
+    for (section_tag, table) in &state.sections {
+        let Some(section) = XMLSection::from_tag(section_tag) else {
+            return Err(Error::Validation(format!("unknown section '{section_tag}'")));
+        };
+        for (tag, value) in table {
+            import_value(&mut config, section, tag, value)?;
+        }
+    }
+    // Exported indexes were written with the exported resolution and rate
+    config.keep_mode_indexes();
+    Ok(config)
+}
+
+fn import_value(config: &mut SystemConfig, section: XMLSection, tag: &str, value: &str) -> Result<()> {
+    let is_catalog = config.settings().iter().any(|setting| setting.section == section && setting.tag == tag);
+    if !is_catalog {
+        return config.set_default(section, tag, value)
+            .map_err(|_| Error::Validation(format!("unknown key '{}.{tag}'", section.tag())));
+    }
+    let setting = config.setting_mut(tag)?;
+    let element = Element::new(Some(section), tag, Value::Attribute(value.into()));
+    xml::read_setting(setting, &element)?;
+    match validation::check_read(setting) {
+        Some(message) => Err(Error::Validation(format!("{}.{tag}: {message}", section.tag()))),
+        None => Ok(()),
+    }
+}
+
+fn to_json(state: &State) -> String {
+    // Top-level values and sections share one sorted key space
+    let mut keys: Vec<&String> = state.top.keys().chain(state.sections.keys()).collect();
+    keys.sort();
+    let mut output = String::from("{\n");
+    for (i, key) in keys.iter().enumerate() {
+        let _ = write!(output, "  {}: ", json_string(key));
+        if let Some(value) = state.top.get(*key) {
+            output.push_str(&json_string(value));
+        } else {
+            output.push_str("{\n");
+            let table = &state.sections[*key];
+            for (j, (tag, value)) in table.iter().enumerate() {
+                let _ = write!(output, "    {}: {}", json_string(tag), json_string(value));
+                output.push_str(if j + 1 < table.len() { ",\n" } else { "\n" });
+            }
+            output.push_str("  }");
+        }
+        output.push_str(if i + 1 < keys.len() { ",\n" } else { "\n" });
+    }
+    output.push_str("}\n");
+    output
+}
+
+fn to_toml(state: &State) -> String {
+    let mut output = String::new();
+    for (key, value) in &state.top {
+        let _ = writeln!(output, "{} = {}", toml_key(key), json_string(value));
+    }
+    for (section, table) in &state.sections {
+        let _ = writeln!(output, "\n[{}]", toml_key(section));
+        for (tag, value) in table {
+            let _ = writeln!(output, "{} = {}", toml_key(tag), json_string(value));
+        }
+    }
+    output
+}
+
+pub(crate) fn toml_key(key: &str) -> String {
+    let is_bare = !key.is_empty() && key.chars().all(|chr| chr.is_ascii_alphanumeric() || chr == '_' || chr == '-');
+    if is_bare {
+        key.to_string()
+    } else {
+        json_string(key)
+    }
+}
+
+/// Quoted string with JSON escapes, also a valid TOML basic string.
+pub(crate) fn json_string(text: &str) -> String {
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push('"');
+    for chr in text.chars() {
+        match chr {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            chr if (chr as u32) < 0x20 => {
+                let _ = write!(quoted, "\\u{:04x}", chr as u32);
+            }
+            chr => quoted.push(chr),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+// Reads a quoted string starting at the opening quote, returns it with the length consumed
+fn read_quoted(text: &str) -> std::result::Result<(String, usize), &'static str> {
+    let mut value = String::new();
+    let mut chars = text.char_indices().skip(1);
+    while let Some((i, chr)) = chars.next() {
+        match chr {
+            '"' => return Ok((value, i + 1)),
+            '\\' => {
+                let Some((_, escaped)) = chars.next() else {
+                    break;
+                };
+                match escaped {
+                    '"' => value.push('"'),
+                    '\\' => value.push('\\'),
+                    '/' => value.push('/'),
+                    'n' => value.push('\n'),
+                    'r' => value.push('\r'),
+                    't' => value.push('\t'),
+                    'b' => value.push('\u{8}'),
+                    'f' => value.push('\u{c}'),
+                    'u' => {
+                        let hex: String = chars.by_ref().take(4).map(|(_, chr)| chr).collect();
+                        let code = u32::from_str_radix(&hex, 16).map_err(|_| "invalid \\u escape")?;
+                        value.push(char::from_u32(code).ok_or("invalid \\u escape")?);
+                    }
+                    _ => return Err("unknown escape sequence"),
+                }
+            }
+            chr => value.push(chr),
+        }
+    }
+    Err("unterminated string")
+}
+
+struct JsonParser<'a> {
+    content: &'a str,
+    position: usize,
+}
+
+impl JsonParser<'_> {
+    fn error(&self, message: &str) -> Error {
+        Error::parse_at(self.content, self.position, message)
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = &self.content[self.position..];
+        self.position += rest.len() - rest.trim_start().len();
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.skip_whitespace();
+        self.content[self.position..].chars().next()
+    }
+
+    fn expect(&mut self, expected: char) -> Result<()> {
+        if self.peek() != Some(expected) {
+            return Err(self.error(&format!("expected '{expected}'")));
+        }
+        self.position += 1;
+        Ok(())
+    }
+
+    fn parse(mut self) -> Result<State> {
+        let mut state = State { top: Table::new(), sections: BTreeMap::new() };
+        self.expect('{')?;
+        while let Some(key) = self.key()? {
+            if self.peek() == Some('{') {
+                self.position += 1;
+                let mut table = Table::new();
+                while let Some(tag) = self.key()? {
+                    let value = self.scalar()?;
+                    if table.insert(tag.clone(), value).is_some() {
+                        return Err(self.error(&format!("duplicate key '{tag}'")));
+                    }
+                    self.separator('}')?;
+                }
+                if state.sections.insert(key.clone(), table).is_some() {
+                    return Err(self.error(&format!("duplicate key '{key}'")));
+                }
+            } else {
+                let value = self.scalar()?;
+                if state.top.insert(key.clone(), value).is_some() {
+                    return Err(self.error(&format!("duplicate key '{key}'")));
+                }
+            }
+            self.separator('}')?;
+        }
+        if self.peek().is_some() {
+            return Err(self.error("trailing content after the object"));
+        }
+        Ok(state)
+    }
+
+    // Reads `"key":` of the next pair, `None` once the object is closed
+    fn key(&mut self) -> Result<Option<String>> {
+        if self.peek() == Some('}') {
+            self.position += 1;
+            return Ok(None);
+        }
+        let key = self.string()?;
+        self.expect(':')?;
+        Ok(Some(key))
+    }
+
+    fn separator(&mut self, closing: char) -> Result<()> {
+        match self.peek() {
+            Some(',') => {
+                self.position += 1;
+                if self.peek() == Some(closing) {
+                    return Err(self.error("trailing comma"));
+                }
+                Ok(())
+            }
+            Some(chr) if chr == closing => Ok(()),
+            _ => Err(self.error(&format!("expected ',' or '{closing}'"))),
+        }
+    }
+
+    fn string(&mut self) -> Result<String> {
+        if self.peek() != Some('"') {
+            return Err(self.error("expected a string"));
+        }
+        match read_quoted(&self.content[self.position..]) {
+            Ok((value, length)) => {
+                self.position += length;
+                Ok(value)
+            }
+            Err(message) => Err(self.error(message)),
+        }
+    }
+
+    // Numbers and booleans are accepted and kept as their text
+    fn scalar(&mut self) -> Result<String> {
+        if self.peek() == Some('"') {
+            return self.string();
+        }
+        let rest = &self.content[self.position..];
+        let length = rest.find(|chr: char| chr == ',' || chr == '}' || chr.is_whitespace()).unwrap_or(rest.len());
+        let raw = &rest[..length];
+        let is_scalar = raw == "true" || raw == "false" || (!raw.is_empty() && raw.parse::<f64>().is_ok());
+        if !is_scalar {
+            return Err(self.error("expected a string, number or boolean"));
+        }
+        self.position += length;
+        Ok(raw.to_string())
+    }
+}
+
+pub(crate) fn parse_toml(content: &str) -> Result<State> {
+    let mut state = State { top: Table::new(), sections: BTreeMap::new() };
+    let mut section: Option<String> = None;
+    let mut offset = 0;
+    for line in content.split_inclusive('\n') {
+        let line_offset = offset;
+        offset += line.len();
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        let error = |message: &str| Error::parse_at(content, line_offset, message);
+        if let Some(header) = trimmed.strip_prefix('[') {
+            let Some(name) = header.strip_suffix(']') else {
+                return Err(error("unterminated table header"));
+            };
+            let name = toml_key_text(name.trim()).map_err(error)?;
+            state.sections.entry(name.clone()).or_default();
+            section = Some(name);
+            continue;
+        }
+        let (key, value) = split_toml_pair(trimmed).map_err(error)?;
+        let table = match &section {
+            Some(name) => state.sections.entry(name.clone()).or_default(),
+            None => &mut state.top,
+        };
+        if table.insert(key.clone(), value).is_some() {
+            return Err(error(&format!("duplicate key '{key}'")));
+        }
+    }
+    Ok(state)
+}
+
+fn split_toml_pair(line: &str) -> std::result::Result<(String, String), &'static str> {
+    let (key, rest) = if line.starts_with('"') {
+        let (key, length) = read_quoted(line)?;
+        (key, line[length..].trim_start())
+    } else {
+        let Some(equals) = line.find('=') else {
+            return Err("expected 'key = value'");
+        };
+        (toml_key_text(line[..equals].trim())?, &line[equals..])
+    };
+    let Some(rest) = rest.strip_prefix('=') else {
+        return Err("expected '='");
+    };
+    let rest = rest.trim();
+    if rest.starts_with('"') {
+        let (value, length) = read_quoted(rest)?;
+        let trailing = rest[length..].trim();
+        if !trailing.is_empty() && !trailing.starts_with('#') {
+            return Err("unexpected content after the value");
+        }
+        return Ok((key, value));
+    }
+    let raw = rest.split('#').next().unwrap_or("").trim();
+    let is_scalar = raw == "true" || raw == "false" || (!raw.is_empty() && raw.parse::<f64>().is_ok());
+    if !is_scalar {
+        return Err("expected a string, number or boolean");
+    }
+    Ok((key, raw.to_string()))
+}
+
+fn toml_key_text(key: &str) -> std::result::Result<String, &'static str> {
+    if key.starts_with('"') {
+        let (key, _) = read_quoted(key)?;
+        return Ok(key);
+    }
+    if key.is_empty() || !key.chars().all(|chr| chr.is_ascii_alphanumeric() || chr == '_' || chr == '-') {
+        return Err("invalid key");
+    }
+    Ok(key.to_string())
+}
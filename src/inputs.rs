@@ -122,12 +122,21 @@ pub(crate) mod windows {
         fn SetConsoleCursorPosition(handle: *const void, cursor_position: Coord) -> i32;
     }
 
-    unsafe fn fallback_read_key(handle: *const void) -> KeyCode {
+    fn fallback_read_key() -> KeyCode {
         use std::io::Read;
 
         let mut buffer = [0u8; 3];
-        std::io::stdin().read(&mut buffer);
-        return KeyCode::Char(char::from_u32_unchecked(buffer[0] as u32));
+        match std::io::stdin().read(&mut buffer) {
+            Ok(0) | Err(_) => KeyCode::Error,
+            Ok(_) => match buffer[0] {
+                b'!'..=b'~' => KeyCode::Char(buffer[0] as char),
+                8       => KeyCode::Backspace,
+                b'\r'   => KeyCode::Enter,
+                b'\n'   => KeyCode::Enter,
+                b' '    => KeyCode::Space,
+                other   => KeyCode::Other(other as u64),
+            },
+        }
     }
 
     pub fn read_key() -> KeyCode {
@@ -146,7 +155,7 @@ pub(crate) mod windows {
 
                 // Reading the console input failed.
                 if result == 0 || entries_read == 0 {
-                    return fallback_read_key(handle);
+                    return fallback_read_key();
                 }
 
                 if input.event_type != 1 {
@@ -163,7 +172,7 @@ pub(crate) mod windows {
                 if key.character_data != 0 {
                     let data = key.character_data;
                     match key.character_data  {
-                        65..=90 | 97..=122 => return KeyCode::Char(char::from_u32_unchecked(data as u32)),
+                        33..=126 => return KeyCode::Char(data as u8 as char),
                         8  => return KeyCode::Backspace,
                         13 => return KeyCode::Enter,
                         32 => return KeyCode::Space,
@@ -221,7 +230,7 @@ pub(crate) mod windows {
 }
 
 
-#[cfg(all(unix))]
+#[cfg(unix)]
 pub(crate) mod unix {
     use super::KeyCode;
 
@@ -274,7 +283,10 @@ pub(crate) mod unix {
         poll(&mut pollfd as *mut PollFd, 1, 0);
         while pollfd.return_events != 0 {
             let mut data: u8 = 0;
-            let _ = read(STDIN, &mut data as *mut u8 as *mut void, 1);
+            // Readable but nothing read means end of input
+            if read(STDIN, &mut data as *mut u8 as *mut void, 1) <= 0 {
+                break;
+            }
 
             let poll_result = poll(&mut pollfd as *mut PollFd, 1, 0);
             if poll_result == -1 {
@@ -295,20 +307,23 @@ pub(crate) mod unix {
             flush_stdin();
 
             let mut data: u64 = 0;
-            let _ = read(STDIN, &mut data as *mut u64 as *mut void, 8);
+            let bytes_read = read(STDIN, &mut data as *mut u64 as *mut void, 8);
 
             tcsetattr(STDIN, TCSANOW, &old_settings as *const Termios);
+            if bytes_read <= 0 {
+                return KeyCode::Error;
+            }
 
             match data {
-                65..=90 | 97..=122 => return KeyCode::Char(char::from_u32_unchecked(data as u32)),
-                10  => return KeyCode::Enter,
-                32  => return KeyCode::Space,
-                127 => return KeyCode::Backspace,
-                0x445b1b => return KeyCode::ArrowLeft,
-                0x415b1b => return KeyCode::ArrowUp,
-                0x435b1b => return KeyCode::ArrowRight,
-                0x425b1b => return KeyCode::ArrowDown,
-                _ => return KeyCode::Other(data),
+                33..=126 => KeyCode::Char(data as u8 as char),
+                10  => KeyCode::Enter,
+                32  => KeyCode::Space,
+                127 => KeyCode::Backspace,
+                0x445b1b => KeyCode::ArrowLeft,
+                0x415b1b => KeyCode::ArrowUp,
+                0x435b1b => KeyCode::ArrowRight,
+                0x425b1b => KeyCode::ArrowDown,
+                _ => KeyCode::Other(data),
             }
         }
     }
@@ -0,0 +1,45 @@
+//! Generates and inspects RDR2 `system.xml` graphics configs.
+//!
+//! - [`settings`] is the catalog of settings in in-game order
+//! - [`config`] is a typed builder over the catalog
+//! - [`xml`] reads and writes `system.xml`
+//! - [`resolution`] has the standard display modes and sets width and height together
+//! - [`vram`] estimates VRAM usage of a configuration
+//! - [`calibration`] fits the VRAM model to readings taken in-game
+//! - [`bench`] reads in-game benchmark results and the settings they were run with
+//! - [`perf`] fits the FPS cost of settings per video card from benchmark results
+//! - [`presets`] applies low to ultra quality presets
+//! - [`validation`] checks values before they're written
+//! - [`rules`] checks which settings need, conflict with or imply each other
+//! - [`lint`] checks existing files against the catalog
+//! - [`export`] exports the full state to JSON or TOML and imports it back
+//! - [`share`] packs the settings into short codes players can paste to each other
+//! - [`sweep`] installs config variants one by one and collects a benchmark for each
+//! - [`report`] renders a Markdown or plain-text settings report
+//! - [`tune`] picks the highest quality that fits a VRAM budget
+//! - [`watch`] notices when the game rewrites or resets `system.xml`
+//! - [`video_card`] finds the video card description the game expects
+
+pub mod bench;
+pub mod calibration;
+pub mod config;
+pub mod error;
+pub mod export;
+pub mod lint;
+pub mod perf;
+pub mod presets;
+pub mod report;
+pub mod resolution;
+pub mod rules;
+pub mod settings;
+pub mod share;
+pub mod sweep;
+pub mod tune;
+pub mod validation;
+pub mod video_card;
+pub mod vram;
+pub mod watch;
+pub mod xml;
+
+pub use config::{Api, DlssQuality, FsrQuality, Level, ScreenType, SystemConfig, VSync};
+pub use error::{Error, Result};
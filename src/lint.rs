@@ -0,0 +1,229 @@
+//! Checks an existing `system.xml` against the catalog.
+//!
+//! Broken hand edits make the game silently reset to defaults, so everything the game
+//! could choke on is reported together with the line it was found on.
+
+use std::fmt::Write;
+use std::path::Path;
+use crate::calibration::Calibration;
+use crate::error::{Error, Result};
+use crate::export::json_string;
+use crate::rules;
+use crate::settings::{self, Rule, Setting, SettingType};
+use crate::validation;
+use crate::xml::{self, Document, Element};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl Severity {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        }
+    }
+}
+
+/// Single problem, `line` and `column` are 0 when it isn't tied to an element.
+#[derive(Clone, Debug)]
+pub struct Finding {
+    pub severity: Severity,
+    pub code: &'static str,
+    pub tag: Option<String>,
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl Finding {
+    fn at(severity: Severity, code: &'static str, element: &Element, message: String) -> Self {
+        Self {
+            severity,
+            code,
+            tag: Some(element.tag.clone()),
+            line: element.line,
+            column: element.column,
+            message,
+        }
+    }
+
+    fn global(severity: Severity, code: &'static str, tag: Option<&str>, message: String) -> Self {
+        Self { severity, code, tag: tag.map(String::from), line: 0, column: 0, message }
+    }
+}
+
+/// Lints a file, a file that can't be parsed at all is reported as a single finding.
+/// A read-only file is reported too, the game then can't save changes made in its menus.
+pub fn lint_file(path: &Path, vram_budget: usize, calibration: &Calibration) -> Result<Vec<Finding>> {
+    let mut findings = match Document::read_file(path) {
+        Ok(document) => lint_document(&document, vram_budget, calibration),
+        Err(Error::Parse { line, column, message }) => {
+            vec![Finding { severity: Severity::Error, code: "parse", tag: None, line, column, message }]
+        }
+        Err(err) => return Err(err),
+    };
+    if xml::is_read_only(path)? {
+        let message = "file is read-only, the game keeps these settings but can't save changes made in its menus".into();
+        findings.push(Finding::global(Severity::Warning, "read-only", None, message));
+    }
+    Ok(findings)
+}
+
+/// Every problem in the document, the VRAM budget is checked with the calibration's estimate.
+pub fn lint_document(document: &Document, vram_budget: usize, calibration: &Calibration) -> Vec<Finding> {
+    let mut findings = vec![];
+    if document.root != xml::ROOT_TAG {
+        let message = format!("root element is <{}>, expected <{}>", document.root, xml::ROOT_TAG);
+        findings.push(Finding::global(Severity::Error, "wrong-root", None, message));
+    }
+    match document.find(None, "version") {
+        Some(element) if element.value.as_str() != xml::VERSION => {
+            let message = format!("version is {}, expected {}", element.value.as_str(), xml::VERSION);
+            findings.push(Finding::at(Severity::Error, "wrong-version", element, message));
+        }
+        Some(_) => {}
+        None => findings.push(Finding::global(Severity::Error, "wrong-version", Some("version"), "version is missing".into())),
+    }
+    if document.video_card().is_none() {
+        let message = "videoCardDescription is missing or empty, the game will reset the config".into();
+        let finding = match document.find(None, "videoCardDescription") {
+            Some(element) => Finding::at(Severity::Error, "missing-video-card", element, message),
+            None => Finding::global(Severity::Error, "missing-video-card", Some("videoCardDescription"), message),
+        };
+        findings.push(finding);
+    }
+
+    let mut settings = settings::get_settings();
+    let defaults = xml::default_elements();
+    for (i, element) in document.elements.iter().enumerate() {
+        let duplicate = document.elements[..i].iter().any(|earlier| earlier.section == element.section && earlier.tag == element.tag);
+        if duplicate {
+            findings.push(Finding::at(Severity::Error, "duplicate", element, format!("{} appears more than once", element.tag)));
+        }
+        if element.section.is_none() {
+            if !["version", "configSource", "videoCardDescription"].contains(&element.tag.as_str()) {
+                findings.push(Finding::at(Severity::Warning, "unknown-tag", element, format!("unknown top-level element {}", element.tag)));
+            }
+            continue;
+        }
+        let is_default = defaults.iter().any(|default| default.section == element.section && default.tag == element.tag);
+        let catalog_index = settings.iter().position(|setting| Some(setting.section) == element.section && setting.tag == element.tag);
+        match catalog_index {
+            Some(index) if !duplicate => {
+                let element = document.setting_element(&settings[index]).unwrap_or_else(|| element.clone());
+                lint_setting(&mut settings[index], &element, &mut findings);
+            }
+            Some(_) => {}
+            None if is_default => {}
+            None => findings.push(Finding::at(Severity::Warning, "unknown-tag", element, format!("unknown element {}", element.tag))),
+        }
+    }
+
+    for setting in &settings {
+        if document.find(Some(setting.section), &setting.tag).is_none() {
+            let message = format!("{} is missing from {}, the game will use its default", setting.tag, setting.section.tag());
+            findings.push(Finding::global(Severity::Warning, "missing-setting", Some(&setting.tag), message));
+        }
+    }
+
+    for element in &document.elements {
+        let Some(section) = element.section else {
+            continue;
+        };
+        let Some(expected) = xml::derived_value(section, &element.tag, &settings) else {
+            continue;
+        };
+        if element.value.as_str() != expected.as_str() {
+            let message = format!("{} is {} but the windowed resolution says {}, the selector writes them in step",
+                element.tag, element.value.as_str(), expected.as_str());
+            findings.push(Finding::at(Severity::Warning, "inconsistent-resolution", element, message));
+        }
+    }
+
+    let mut hidden = defaults;
+    document.apply_to_defaults(&mut hidden);
+    for violation in rules::check(&settings, &hidden) {
+        let code = match violation.rule {
+            Rule::Requires(..) => "rule-requires",
+            Rule::Conflicts(..) => "rule-conflicts",
+            Rule::Implies(..) => "rule-implies",
+            Rule::OnlyWith(..) => "rule-only-with",
+        };
+        let finding = match document.elements.iter().find(|element| element.tag == violation.tag) {
+            Some(element) => Finding::at(Severity::Warning, code, element, violation.message),
+            None => Finding::global(Severity::Warning, code, Some(violation.tag), violation.message),
+        };
+        findings.push(finding);
+    }
+
+    let estimate = calibration.estimate(&settings);
+    if estimate > vram_budget as f64 {
+        let message = format!("estimated VRAM usage {estimate:.0} MB is over the budget of {vram_budget} MB");
+        findings.push(Finding::global(Severity::Warning, "vram-over-budget", None, message));
+    }
+    findings
+}
+
+// Reads the value into the catalog setting so the VRAM estimate reflects the file
+fn lint_setting(setting: &mut Setting, element: &Element, findings: &mut Vec<Finding>) {
+    let mut candidate = setting.clone();
+    if let Err(err) = xml::read_setting(&mut candidate, element) {
+        let message = match err {
+            Error::Parse { message, .. } => message,
+            other => other.to_string(),
+        };
+        findings.push(Finding::at(Severity::Error, "invalid-value", element, message));
+        return;
+    }
+    if let Some(issue) = validation::check_settings(std::slice::from_ref(&candidate)).into_iter().next() {
+        findings.push(Finding::at(Severity::Error, "invalid-value", element, issue.message));
+        return;
+    }
+    // The game takes any size, the picker only steps in multiples of the jump
+    if let SettingType::Slider(value, jump, _) = candidate.setting_type {
+        if value % jump != 0 {
+            let message = format!("{value} is not a multiple of {jump} like the standard sizes");
+            findings.push(Finding::at(Severity::Warning, "uncommon-size", element, message));
+        }
+    }
+    *setting = candidate;
+}
+
+pub fn has_errors(findings: &[Finding]) -> bool {
+    findings.iter().any(|finding| finding.severity == Severity::Error)
+}
+
+/// One finding per line in the `file:line:column: severity[code]: message` form compilers use.
+pub fn format_text(path: &str, findings: &[Finding]) -> String {
+    let mut output = String::new();
+    for finding in findings {
+        let _ = writeln!(output, "{path}:{}:{}: {}[{}]: {}",
+            finding.line, finding.column, finding.severity.name(), finding.code, finding.message);
+    }
+    output
+}
+
+/// Findings as a JSON array, keys in a fixed order.
+pub fn format_json(path: &str, findings: &[Finding]) -> String {
+    let mut output = String::from("[");
+    for (i, finding) in findings.iter().enumerate() {
+        if i > 0 {
+            output.push(',');
+        }
+        let tag = match &finding.tag {
+            Some(tag) => json_string(tag),
+            None => "null".into(),
+        };
+        let _ = write!(output, "\n  {{\"file\": {}, \"line\": {}, \"column\": {}, \"severity\": \"{}\", \"code\": \"{}\", \"tag\": {tag}, \"message\": {}}}",
+            json_string(path), finding.line, finding.column, finding.severity.name(), finding.code, json_string(&finding.message));
+    }
+    if !findings.is_empty() {
+        output.push('\n');
+    }
+    output.push(']');
+    output
+}
@@ -1,43 +1,738 @@
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use rdr2_gfx_selector::{bench, calibration, export, lint, perf, report, resolution, rules, settings, share, sweep, tune, validation, video_card, vram, watch, xml, Error, Result, SystemConfig};
+use rdr2_gfx_selector::calibration::Calibration;
+use rdr2_gfx_selector::export::Format;
+use rdr2_gfx_selector::perf::PerfCosts;
+use rdr2_gfx_selector::report::ReportFormat;
+use rdr2_gfx_selector::tune::TuneOptions;
+use rdr2_gfx_selector::settings::{Rule, Setting, SettingType};
+use rdr2_gfx_selector::video_card::{CardCheck, VideoCardDescription};
+use rdr2_gfx_selector::watch::Change;
 use crate::inputs::KeyCode;
-use crate::settings::{Setting, SettingType};
 
 #[cfg(target_os = "windows")]
 use crate::inputs::windows::{read_key, clear_console};
 
-#[cfg(all(unix))]
+#[cfg(unix)]
 use crate::inputs::unix::{read_key, clear_console};
-use crate::settings::SettingType::{Level, Multiplier, OnHalfOff, OnOff, Slider};
 
-mod settings;
 mod inputs;
 
-// Highest low [test on minimal settings (1024 x 768)] claimed=1537
-const MIN_VRAM: usize = 1670;
-const RECOMMENDED_VRAM: usize = 6144;
 const PADDING: usize = 40;
+const VALUE_WIDTH: usize = 14;
+const OUTPUT_FILE: &str = "system.xml";
+
+const USAGE: &str = "\
+Usage: rdr2-gfx-selector [--vram <MB>] [--read-only]
+       rdr2-gfx-selector lint <file> [--vram <MB>] [--json]
+       rdr2-gfx-selector export <file> [--format json|toml] [--output <file>]
+       rdr2-gfx-selector import <file> [--format json|toml] [--output <file>] [--force] [--read-only]
+       rdr2-gfx-selector export-code <file>
+       rdr2-gfx-selector import-code <code> [--base <file>] [--output <file>] [--force] [--read-only]
+       rdr2-gfx-selector set <tag=value>... [--base <file>] [--output <file>] [--force] [--read-only]
+       rdr2-gfx-selector report <file> [--text] [--gpu]
+       rdr2-gfx-selector tune [--vram <MB>] [--resolution <WxH>] [--weight <tag=N>]... [--lock <tag>]... [--base <file>] [--output <file>] [--read-only]
+       rdr2-gfx-selector frontier [--vram <MB>] [--resolution <WxH>] [--weight <tag=N>]... [--lock <tag>]... [--base <file>]
+       rdr2-gfx-selector calibrate record <file> --mb <MB> [--dataset <file>]
+       rdr2-gfx-selector calibrate fit [--dataset <file>] [--output <file>] [--dry-run]
+       rdr2-gfx-selector bench list [--dir <dir>]
+       rdr2-gfx-selector bench show <file> [--dir <dir>]
+       rdr2-gfx-selector watch [--profile <file>] [--dir <dir>] [--reapply] [--read-only] [--poll] [--interval <ms>]
+       rdr2-gfx-selector perf fit [--dir <dir>] [--dry-run]
+       rdr2-gfx-selector sweep --vary <tag=a,b,...>... [--base <file>] [--dir <dir>] [--output <file>] [--interval <ms>] [--timeout <s>]
+
+Commands:
+  lint <file>        Check a system.xml against the settings catalog
+  export <file>      Export a system.xml as sorted JSON or TOML, printed unless --output is given
+  import <file>      Turn a JSON or TOML export back into system.xml
+  export-code <file> Print a short code with every managed setting, without the video card name
+  import-code <code> Apply a code on top of --base (the installed system.xml by default)
+  set <tag=value>... Change values of --base as they're written in the file, checked against the
+                     catalog rules: implied values follow, needed or conflicting ones are refused
+  report <file>      Print a Markdown settings report to share when asking for help
+  tune               Pick the highest quality that fits in --vram, printed unless --output is given
+  frontier           List every config no other beats on both VRAM and quality, up to --vram,
+                     each line showing what changed from the one before
+  calibrate record   Add the settings of <file> and the VRAM the game reported for them to the readings
+  calibrate fit      Fit the VRAM costs to the readings, print how well they match and write the override
+                     used from then on (calibration/catalog.toml)
+  bench list         List the in-game benchmark results grouped by the settings they were run with
+  bench show <file>  Print the FPS and frame times of each pass of a result and its settings
+  watch              Report every time the game rewrites system.xml in the settings directory, what differs
+                     from --profile (the file as it was when watching started) and whether it looks like a reset
+  perf fit           Fit the FPS cost of each setting step per video card from the benchmark results
+                     and write them to perf/<card>.toml, shown next to the VRAM cost in the selector
+  sweep              Install --base and then each --vary value on top of it into the settings directory,
+                     backing up the previous system.xml, and wait for a benchmark report after each.
+                     The results are written to --output (sweep.csv) and the base is put back at the end
+
+Options:
+  --vram <MB>        VRAM available on the video card, defaults to 6144
+  --json             Print lint findings as JSON
+  --format <format>  json or toml, guessed from the file extension otherwise
+  --output <file>    Where to write, import defaults to system.xml
+  --base <file>      File a code or values are applied to, its video card name is kept
+  --force            Write values that break a rule or a video card name other than the installed one
+  --read-only        Mark the written system.xml read-only so the game can't revert it, it's made writable
+                     again before the next write. Settings changed in the game's menus won't be saved
+  --text             Plain-text report instead of Markdown
+  --gpu              Include the video card name in the report
+  --mb <MB>          VRAM the game reported for the settings being recorded
+  --dataset <file>   Calibration readings, defaults to calibration/readings.txt
+  --dry-run          Print the fit without writing the override or the perf costs
+  --dir <dir>        Game settings directory holding system.xml and Benchmarks, defaults to the one in Documents
+  --vary <tag=a,b>   Values to sweep a setting over, as they're written in the file, e.g. msaa=0,2,4
+  --interval <ms>    How often to look for a new benchmark report or, without inotify, a changed system.xml,
+                     defaults to 1000
+  --profile <file>   Settings watch compares system.xml with
+  --reapply          Write --profile back whenever the game changes system.xml, keeping its video card name
+  --poll             Check system.xml every --interval instead of using inotify, for network and FUSE mounts
+  --timeout <s>      Stop when a report takes longer than this, waits forever by default
+  --resolution <WxH> Resolution to tune for, e.g. 2560x1440 or 1440p
+  --weight <tag=N>   How much a setting matters to the tuner, e.g. textureQuality=10
+  --lock <tag>       Keep a setting at its current value while tuning
+  --help             Print this message";
 
 fn main() {
-    println!("Running!");
-    // key_testing();
-    // let args: Vec<String> = std::env::args().collect();
-    start_console(RECOMMENDED_VRAM);
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Err(err) = run(args) {
+        eprintln!("{err}");
+        std::process::exit(1);
+    }
+}
+
+fn run(mut args: Vec<String>) -> Result<()> {
+    if take_flag(&mut args, "--help") || take_flag(&mut args, "-h") {
+        println!("{USAGE}");
+        return Ok(());
+    }
+    let calibration = load_calibration();
+    let vram_available = match take_option(&mut args, "--vram")? {
+        Some(value) => parse_number(&value, "--vram")?,
+        None => vram::RECOMMENDED_VRAM,
+    };
+    let read_only = take_flag(&mut args, "--read-only");
+    let command = if args.is_empty() { String::new() } else { args.remove(0) };
+    match command.as_str() {
+        "" => {
+            println!("Running!");
+            // key_testing();
+            start_console(vram_available, read_only, &calibration)
+        }
+        "lint" => lint_command(args, vram_available, &calibration),
+        "export" => export_command(args),
+        "import" => import_command(args, read_only),
+        "export-code" => export_code_command(args),
+        "import-code" => import_code_command(args, read_only),
+        "set" => set_command(args, read_only),
+        "report" => report_command(args, &calibration),
+        "tune" => tune_command(args, vram_available, read_only, &calibration),
+        "frontier" => frontier_command(args, vram_available, &calibration),
+        "calibrate" => calibrate_command(args, &calibration),
+        "bench" => bench_command(args),
+        "sweep" => sweep_command(args),
+        "perf" => perf_command(args),
+        "watch" => watch_command(args, read_only),
+        _ => Err(Error::Validation(format!("unknown command '{command}'\n{USAGE}"))),
+    }
+}
+
+// A broken override shouldn't lock anyone out, the built-in costs are used instead
+fn load_calibration() -> Calibration {
+    let path = Path::new(calibration::OVERRIDE_FILE);
+    if !path.exists() {
+        return Calibration::builtin();
+    }
+    match Calibration::read_file(path) {
+        Ok(fitted) => fitted,
+        Err(err) => {
+            eprintln!("Ignoring {}: {err}", calibration::OVERRIDE_FILE);
+            Calibration::builtin()
+        }
+    }
+}
+
+fn lint_command(mut args: Vec<String>, vram_budget: usize, calibration: &Calibration) -> Result<()> {
+    let json = take_flag(&mut args, "--json");
+    let path = single_positional(args, "lint <file>")?;
+    let findings = lint::lint_file(Path::new(&path), vram_budget, calibration)?;
+    if json {
+        println!("{}", lint::format_json(&path, &findings));
+    } else if findings.is_empty() {
+        println!("{path}: no problems found");
+    } else {
+        print!("{}", lint::format_text(&path, &findings));
+    }
+    if lint::has_errors(&findings) {
+        return Err(Error::Validation(format!("{path} would be reset by the game")));
+    }
+    Ok(())
+}
+
+fn export_command(mut args: Vec<String>) -> Result<()> {
+    let format = take_option(&mut args, "--format")?;
+    let output = take_option(&mut args, "--output")?;
+    let path = single_positional(args, "export <file>")?;
+    let format = match (&format, &output) {
+        (Some(name), _) => parse_format(name)?,
+        (None, Some(output)) => Format::from_path(Path::new(output)).unwrap_or(Format::Json),
+        (None, None) => Format::Json,
+    };
+    let config = SystemConfig::read_file(Path::new(&path))?;
+    let content = export::export(&config, format);
+    match output {
+        Some(output) => std::fs::write(output, content)?,
+        None => print!("{content}"),
+    }
+    Ok(())
+}
+
+fn import_command(mut args: Vec<String>, read_only: bool) -> Result<()> {
+    let format = take_option(&mut args, "--format")?;
+    let output = take_option(&mut args, "--output")?.unwrap_or(OUTPUT_FILE.into());
+    let force = take_flag(&mut args, "--force");
+    let path = single_positional(args, "import <file>")?;
+    let format = match format {
+        Some(name) => parse_format(&name)?,
+        None => Format::from_path(Path::new(&path))
+            .ok_or_else(|| Error::Validation(format!("can't tell the format of {path}, pass --format")))?,
+    };
+    let content = std::fs::read_to_string(&path)?;
+    let config = export::import(&content, format)?;
+    check_card(&config, force)?;
+    write_config(&config, Path::new(&output), read_only)?;
+    println!("Wrote {output}");
+    Ok(())
+}
+
+// The game resets a config written for another card, so that takes --force like in the selector's confirmation
+fn check_card(config: &SystemConfig, force: bool) -> Result<()> {
+    let mut card = VideoCardDescription::load()?;
+    card.name = config.video_card_name().map(String::from);
+    let card_name = card.require_name()?;
+    if let CardCheck::Mismatch(current) = card.check() {
+        let message = format!("the video card is '{card_name}' but the installed system.xml has '{current}', the game will reset this config");
+        if !force {
+            return Err(Error::Validation(format!("{message}, add --force to write it anyway")));
+        }
+        println!("warning: {message}");
+    }
+    Ok(())
+}
+
+fn export_code_command(args: Vec<String>) -> Result<()> {
+    let path = single_positional(args, "export-code <file>")?;
+    let config = SystemConfig::read_file(Path::new(&path))?;
+    println!("{}", share::encode(config.settings())?);
+    Ok(())
+}
+
+fn import_code_command(mut args: Vec<String>, read_only: bool) -> Result<()> {
+    let base = take_option(&mut args, "--base")?;
+    let output = take_option(&mut args, "--output")?.unwrap_or(OUTPUT_FILE.into());
+    let force = take_flag(&mut args, "--force");
+    let code = single_positional(args, "import-code <code>")?;
+    let mut config = base_config(base)?;
+    share::decode(&code, config.settings_mut())?;
+    check_card(&config, force)?;
+    write_config(&config, Path::new(&output), read_only)?;
+    println!("Wrote {output}");
+    Ok(())
 }
 
-fn start_console(vram_available_mbs: usize) {
-    let mut settings = settings::get_settings();
-    let capacity = settings_string_capacity(&settings);
-    // hacky code since both settings are dependent on each other
-    let width_index = settings::get_setting_index_by_tag(&settings, "screenWidthWindowed").unwrap();
-    let height_index = settings::get_setting_index_by_tag(&settings, "screenHeightWindowed").unwrap();
+fn set_command(mut args: Vec<String>, read_only: bool) -> Result<()> {
+    let base = take_option(&mut args, "--base")?;
+    let output = take_option(&mut args, "--output")?.unwrap_or(OUTPUT_FILE.into());
+    let force = take_flag(&mut args, "--force");
+    if args.is_empty() {
+        return Err(Error::Validation(format!("set expects at least one tag=value\n{USAGE}")));
+    }
+    let mut config = base_config(base)?;
+    let mut pairs = vec![];
+    for arg in &args {
+        let Some((tag, value)) = arg.split_once('=') else {
+            return Err(Error::Validation(format!("set expects tag=value, got '{arg}'")));
+        };
+        pairs.push((tag.trim().to_string(), value.trim().to_string()));
+    }
+    // The API goes first so its recommended companions don't overwrite values given explicitly
+    pairs.sort_by_key(|(tag, _)| tag != "API");
+    let mut tags = vec![];
+    for (tag, value) in &pairs {
+        config.set_value(tag, value)?;
+        if tag == "API" {
+            for index in settings::apply_api_defaults(config.settings_mut()) {
+                let setting = &config.settings()[index];
+                if pairs.iter().any(|(tag, _)| *tag == setting.tag) {
+                    continue;
+                }
+                println!("Also set {}={}, recommended for the API", setting.tag, xml::setting_text(setting));
+            }
+        }
+        tags.push(tag.clone());
+    }
+    for implied in rules::apply_implied(&mut config)? {
+        println!("Also set {implied}, the game uses it with the new values");
+    }
+    // Only rules about what was set, older problems in the base are lint's business.
+    // Turning a setting off is fine, what depends on it just stops having an effect
+    let violations: Vec<rules::Violation> = rules::check(config.settings(), config.defaults()).into_iter()
+        .filter(|violation| match violation.rule {
+            Rule::Requires(dependent, _) | Rule::OnlyWith(dependent, _, _) => tags.iter().any(|tag| tag == dependent),
+            Rule::Conflicts(first, second) => tags.iter().any(|tag| tag == first || tag == second),
+            Rule::Implies(..) => false,
+        })
+        .collect();
+    for violation in &violations {
+        println!("{}: {}", if force { "warning" } else { "error" }, violation.message);
+    }
+    if !violations.is_empty() && !force {
+        return Err(Error::Validation("the new values break the rules above, add --force to write them anyway".into()));
+    }
+    check_card(&config, force)?;
+    write_config(&config, Path::new(&output), read_only)?;
+    println!("Wrote {output}");
+    Ok(())
+}
+
+fn write_config(config: &SystemConfig, path: &Path, read_only: bool) -> Result<()> {
+    if read_only {
+        config.write_file_read_only(path)
+    } else {
+        config.write_file(path)
+    }
+}
+
+// --base, the installed system.xml or the defaults
+fn base_config(base: Option<String>) -> Result<SystemConfig> {
+    let installed = video_card::settings_dir().map(|dir| dir.join("system.xml"));
+    match (base, installed) {
+        (Some(base), _) => SystemConfig::read_file(Path::new(&base)),
+        (None, Some(installed)) if installed.exists() => SystemConfig::read_file(&installed),
+        _ => Ok(SystemConfig::new()),
+    }
+}
+
+fn report_command(mut args: Vec<String>, calibration: &Calibration) -> Result<()> {
+    let format = match take_flag(&mut args, "--text") {
+        true => ReportFormat::Text,
+        false => ReportFormat::Markdown,
+    };
+    let include_gpu = take_flag(&mut args, "--gpu");
+    let path = single_positional(args, "report <file>")?;
+    let config = SystemConfig::read_file(Path::new(&path))?;
+    print!("{}", report::render(&config, format, include_gpu, calibration));
+    Ok(())
+}
+
+fn tune_command(mut args: Vec<String>, vram_budget: usize, read_only: bool, calibration: &Calibration) -> Result<()> {
+    let output = take_option(&mut args, "--output")?;
+    let (mut config, options) = tune_inputs(args, vram_budget, calibration)?;
+    let estimate = tune::tune(config.settings_mut(), &options)?;
+    match output {
+        Some(output) => {
+            write_config(&config, Path::new(&output), read_only)?;
+            println!("Wrote {output}, estimated VRAM {estimate:.0} / {vram_budget} MB");
+        }
+        None => print!("{}", report::render(&config, ReportFormat::Text, false, calibration)),
+    }
+    Ok(())
+}
+
+fn frontier_command(args: Vec<String>, vram_budget: usize, calibration: &Calibration) -> Result<()> {
+    let (config, options) = tune_inputs(args, vram_budget, calibration)?;
+    let points = tune::frontier(config.settings(), &options)?;
+    let mut previous: Option<&[Setting]> = None;
+    for point in &points {
+        let mut changes = vec![];
+        for (i, setting) in point.settings.iter().enumerate() {
+            if tune::tunable_values(setting).is_none() || options.locked.contains(&setting.tag) {
+                continue;
+            }
+            let value = value_text(&setting.setting_type);
+            if previous.is_none_or(|previous| value_text(&previous[i].setting_type) != value) {
+                changes.push(format!("{} {value}", setting.nice_name));
+            }
+        }
+        println!("{:>6.0} MB  score {:>4}  {}", point.vram, point.score, changes.join(", "));
+        previous = Some(&point.settings);
+    }
+    Ok(())
+}
+
+// Base config and tuner options shared by tune and frontier
+fn tune_inputs(mut args: Vec<String>, vram_budget: usize, calibration: &Calibration) -> Result<(SystemConfig, TuneOptions)> {
+    let resolution = take_option(&mut args, "--resolution")?;
+    let base = take_option(&mut args, "--base")?;
+    let mut options = TuneOptions::new(vram_budget).calibration(calibration.clone());
+    while let Some(weight) = take_option(&mut args, "--weight")? {
+        let Some((tag, value)) = weight.split_once('=') else {
+            return Err(Error::Validation(format!("--weight expects tag=N, got '{weight}'")));
+        };
+        options = options.weight(tag, parse_number(value, "--weight")? as u32);
+    }
+    while let Some(tag) = take_option(&mut args, "--lock")? {
+        options = options.lock(&tag);
+    }
+    if !args.is_empty() {
+        return Err(Error::Validation(format!("unexpected arguments: {}", args.join(" "))));
+    }
+    let mut config = base_config(base)?;
+    if let Some(resolution) = resolution {
+        config = config.resolution_named(&resolution);
+        config.check_settings()?;
+    }
+    for tag in options.locked.iter().chain(options.weights.iter().map(|(tag, _)| tag)) {
+        config.setting(tag)?;
+    }
+    Ok((config, options))
+}
+
+fn calibrate_command(mut args: Vec<String>, calibration: &Calibration) -> Result<()> {
+    let dataset = take_option(&mut args, "--dataset")?.unwrap_or(calibration::DATASET_FILE.into());
+    let subcommand = if args.is_empty() { String::new() } else { args.remove(0) };
+    match subcommand.as_str() {
+        "record" => {
+            let measured = take_option(&mut args, "--mb")?
+                .ok_or_else(|| Error::Validation("calibrate record needs --mb with the VRAM the game reported".into()))?;
+            let measured = parse_number(&measured, "--mb")?;
+            let path = single_positional(args, "calibrate record <file> --mb <MB>")?;
+            let config = SystemConfig::read_file(Path::new(&path))?;
+            calibration::record(Path::new(&dataset), measured as f64, config.settings())?;
+            println!("Recorded {measured} MB for {path} in {dataset}");
+            Ok(())
+        }
+        "fit" => {
+            let output = take_option(&mut args, "--output")?.unwrap_or(calibration::OVERRIDE_FILE.into());
+            let dry_run = take_flag(&mut args, "--dry-run");
+            if !args.is_empty() {
+                return Err(Error::Validation(format!("unexpected arguments: {}", args.join(" "))));
+            }
+            let readings = calibration::read_dataset(Path::new(&dataset))?;
+            let fit = calibration::fit(&readings, calibration)?;
+            print!("{}", calibration::format_report(&fit));
+            if !dry_run {
+                let comment = format!("Fitted from {} readings in {dataset}, RMSE {:.1} MB", readings.len(), fit.rmse);
+                if let Some(dir) = Path::new(&output).parent().filter(|dir| !dir.as_os_str().is_empty()) {
+                    std::fs::create_dir_all(dir)?;
+                }
+                std::fs::write(&output, fit.calibration.to_toml(&comment))?;
+                println!("\nWrote {output}");
+            }
+            Ok(())
+        }
+        _ => Err(Error::Validation(format!("unknown calibrate command '{subcommand}', expected record or fit"))),
+    }
+}
+
+fn bench_command(mut args: Vec<String>) -> Result<()> {
+    let settings_dir = match take_option(&mut args, "--dir")? {
+        Some(dir) => PathBuf::from(dir),
+        None => video_card::settings_dir().ok_or_else(|| Error::Validation("no home directory, pass --dir".into()))?,
+    };
+    let benchmarks_dir = settings_dir.join(bench::BENCHMARKS_DIR);
+    let configs = bench::config_files(&settings_dir, Path::new(video_card::PROFILES_DIR));
+    let subcommand = if args.is_empty() { String::new() } else { args.remove(0) };
+    match subcommand.as_str() {
+        "list" => {
+            if !args.is_empty() {
+                return Err(Error::Validation(format!("unexpected arguments: {}", args.join(" "))));
+            }
+            let (benchmarks, broken) = bench::read_dir(&benchmarks_dir)?;
+            for (path, err) in broken {
+                eprintln!("Skipping {}: {err}", path.display());
+            }
+            if benchmarks.is_empty() {
+                println!("No benchmark results in {}", benchmarks_dir.display());
+                return Ok(());
+            }
+            // Grouped by settings in the order they were first used
+            let mut groups: Vec<(Option<PathBuf>, Vec<&bench::Benchmark>)> = vec![];
+            for benchmark in &benchmarks {
+                let settings = benchmark.settings_path(&configs);
+                match groups.iter_mut().find(|(path, _)| *path == settings) {
+                    Some((_, runs)) => runs.push(benchmark),
+                    None => groups.push((settings, vec![benchmark])),
+                }
+            }
+            for (settings, runs) in groups {
+                println!("{}", settings.map_or("Unknown settings".into(), |path| path.display().to_string()));
+                for run in runs {
+                    let name = run.path.file_name().unwrap_or_default().to_string_lossy();
+                    println!("  {}  avg {:>6.1} fps  min {:>6.1} fps  {name}", bench::format_time(run.modified), run.avg_fps(), run.min_fps());
+                }
+            }
+            Ok(())
+        }
+        "show" => {
+            let file = single_positional(args, "bench show <file>")?;
+            let mut path = PathBuf::from(&file);
+            if !path.exists() && path.is_relative() {
+                path = benchmarks_dir.join(&file);
+            }
+            let benchmark = bench::Benchmark::read_file(&path)?;
+            println!("{} ({})", path.display(), bench::format_time(benchmark.modified));
+            println!("{:<12}{:>10}{:>10}{:>10}{:>14}", "Pass", "Min fps", "Max fps", "Avg fps", "Avg frame ms");
+            for pass in &benchmark.passes {
+                let frame_time = pass.frame_time.map_or("-".into(), |stats| format!("{:.2}", stats.avg));
+                println!("{:<12}{:>10.1}{:>10.1}{:>10.1}{frame_time:>14}", pass.name, pass.fps.min, pass.fps.max, pass.fps.avg);
+            }
+            match benchmark.settings_path(&configs) {
+                Some(settings) => match SystemConfig::read_file(&settings).and_then(|config| share::encode(config.settings())) {
+                    Ok(code) => println!("Settings: {} ({code})", settings.display()),
+                    Err(err) => println!("Settings: {} ({err})", settings.display()),
+                },
+                None => println!("Settings: unknown, no config was written before this run"),
+            }
+            Ok(())
+        }
+        _ => Err(Error::Validation(format!("unknown bench command '{subcommand}', expected list or show"))),
+    }
+}
+
+fn watch_command(mut args: Vec<String>, read_only: bool) -> Result<()> {
+    let settings_dir = match take_option(&mut args, "--dir")? {
+        Some(dir) => PathBuf::from(dir),
+        None => video_card::settings_dir().ok_or_else(|| Error::Validation("no home directory, pass --dir".into()))?,
+    };
+    let installed = settings_dir.join(sweep::INSTALLED_FILE);
+    let profile_path = take_option(&mut args, "--profile")?.map(PathBuf::from).unwrap_or(installed.clone());
+    let reapply = take_flag(&mut args, "--reapply");
+    let polling = take_flag(&mut args, "--poll");
+    let interval = match take_option(&mut args, "--interval")? {
+        Some(value) => Duration::from_millis(parse_number(&value, "--interval")? as u64),
+        None => Duration::from_secs(1),
+    };
+    if !args.is_empty() {
+        return Err(Error::Validation(format!("unexpected arguments: {}", args.join(" "))));
+    }
+    // Read once, later writes to the profile file are the game's when it's system.xml itself
+    let mut profile = SystemConfig::read_file(&profile_path)?;
+    let mut watcher = if polling {
+        watch::Watcher::polling(&installed, interval)
+    } else {
+        watch::Watcher::new(&installed, interval)
+    };
+    let method = if watcher.uses_inotify() { "inotify" } else { "polling" };
+    println!("Watching {} ({method}), comparing with {}. Ctrl+C stops", installed.display(), profile_path.display());
+    loop {
+        watcher.wait(None)?;
+        let time = bench::format_time(std::time::SystemTime::now());
+        let found = match watch::check(&profile, &installed) {
+            Change::Unchanged => continue,
+            Change::Removed => {
+                println!("{time} {} was removed", installed.display());
+                None
+            }
+            Change::Unreadable(err) => {
+                println!("{time} {} can't be read: {err}", installed.display());
+                None
+            }
+            Change::Changed { differences, reset, found } => {
+                let what = if reset { "RESET" } else { "changed" };
+                println!("{time} {} {what}, differences from {}:", installed.display(), profile_path.display());
+                for difference in &differences {
+                    println!("  {}: {} -> {}", difference.tag, difference.expected, difference.found);
+                }
+                Some(found)
+            }
+        };
+        if reapply {
+            let backup = watch::reapply(&mut profile, found.as_ref(), &installed, read_only)?;
+            let backup = backup.map_or(String::new(), |backup| format!(", backup in {}", backup.display()));
+            println!("{time} Re-applied {}{backup}", profile_path.display());
+        }
+    }
+}
+
+fn perf_command(mut args: Vec<String>) -> Result<()> {
+    let settings_dir = match take_option(&mut args, "--dir")? {
+        Some(dir) => PathBuf::from(dir),
+        None => video_card::settings_dir().ok_or_else(|| Error::Validation("no home directory, pass --dir".into()))?,
+    };
+    let dry_run = take_flag(&mut args, "--dry-run");
+    if args.first().map(String::as_str) != Some("fit") || args.len() > 1 {
+        return Err(Error::Validation(format!("expected: perf fit [--dir <dir>] [--dry-run]\n{USAGE}")));
+    }
+    let (benchmarks, broken) = bench::read_dir(&settings_dir.join(bench::BENCHMARKS_DIR))?;
+    for (path, err) in broken {
+        eprintln!("Skipping {}: {err}", path.display());
+    }
+    let configs = bench::config_files(&settings_dir, Path::new(video_card::PROFILES_DIR));
+    let groups = perf::samples(&benchmarks, &configs);
+    if groups.is_empty() {
+        return Err(Error::Validation("no benchmark results with known settings and video card".into()));
+    }
+    for (gpu, samples) in groups {
+        // One card without enough runs shouldn't stop the others
+        let fit = match perf::fit(&gpu, &samples) {
+            Ok(fit) => fit,
+            Err(err) => {
+                eprintln!("{err}");
+                continue;
+            }
+        };
+        print!("{}", perf::format_report(&fit));
+        if !dry_run {
+            let path = perf::costs_path(&gpu);
+            std::fs::create_dir_all(perf::PERF_DIR)?;
+            let comment = format!("Fitted from {} benchmarks, RMSE {:.1} fps", samples.len(), fit.rmse);
+            std::fs::write(&path, fit.costs.to_toml(&comment))?;
+            println!("Wrote {}", path.display());
+        }
+    }
+    Ok(())
+}
+
+fn sweep_command(mut args: Vec<String>) -> Result<()> {
+    let settings_dir = match take_option(&mut args, "--dir")? {
+        Some(dir) => PathBuf::from(dir),
+        None => video_card::settings_dir().ok_or_else(|| Error::Validation("no home directory, pass --dir".into()))?,
+    };
+    let installed = settings_dir.join(sweep::INSTALLED_FILE);
+    let base = match take_option(&mut args, "--base")? {
+        Some(base) => SystemConfig::read_file(Path::new(&base))?,
+        None if installed.exists() => SystemConfig::read_file(&installed)?,
+        None => SystemConfig::new(),
+    };
+    let output = take_option(&mut args, "--output")?.unwrap_or(sweep::CSV_FILE.into());
+    let interval = match take_option(&mut args, "--interval")? {
+        Some(value) => Duration::from_millis(parse_number(&value, "--interval")? as u64),
+        None => Duration::from_secs(1),
+    };
+    let timeout = take_option(&mut args, "--timeout")?
+        .map(|value| parse_number(&value, "--timeout").map(|seconds| Duration::from_secs(seconds as u64)))
+        .transpose()?;
+    let mut varies = vec![];
+    while let Some(vary) = take_option(&mut args, "--vary")? {
+        varies.push(sweep::Vary::parse(&vary)?);
+    }
+    if varies.is_empty() || !args.is_empty() {
+        return Err(Error::Validation(format!("sweep expects --vary <tag=a,b,...> and no other arguments\n{USAGE}")));
+    }
+    // Every variant is built before anything is installed, a typo shouldn't leave half a sweep behind
+    let variants = sweep::variants(&base, &varies)?;
+    base.check()?;
+    let result = sweep_runs(&variants, &settings_dir, &output, interval, timeout);
+    // Put back even when a report never came
+    sweep::install(&variants[0], &settings_dir)?;
+    result?;
+    println!("Wrote {output}, put the base back in {}", installed.display());
+    Ok(())
+}
+
+fn sweep_runs(variants: &[sweep::Variant], settings_dir: &Path, output: &str, interval: Duration, timeout: Option<Duration>) -> Result<()> {
+    let benchmarks_dir = settings_dir.join(bench::BENCHMARKS_DIR);
+    let mut runs = vec![];
+    for (index, variant) in variants.iter().enumerate() {
+        let seen = sweep::existing_reports(&benchmarks_dir);
+        if let Some(backup) = sweep::install(variant, settings_dir)? {
+            println!("Backed up {}", backup.display());
+        }
+        println!("[{}/{}] Installed {}, run the benchmark", index + 1, variants.len(), variant.label());
+        let benchmark = sweep::wait_for_report(&benchmarks_dir, &seen, interval, timeout)?;
+        println!("  avg {:.1} fps, min {:.1} fps ({})", benchmark.avg_fps(), benchmark.min_fps(), benchmark.path.display());
+        runs.push(sweep::Run { variant: variant.clone(), benchmark });
+        // Written after every run so an interrupted sweep keeps what it measured
+        std::fs::write(output, sweep::to_csv(&runs))?;
+    }
+    Ok(())
+}
+
+fn parse_format(name: &str) -> Result<Format> {
+    Format::from_name(name).ok_or_else(|| Error::Validation(format!("unknown format '{name}', expected json or toml")))
+}
+
+fn take_flag(args: &mut Vec<String>, name: &str) -> bool {
+    let Some(position) = args.iter().position(|arg| arg == name) else {
+        return false;
+    };
+    args.remove(position);
+    true
+}
+
+fn take_option(args: &mut Vec<String>, name: &str) -> Result<Option<String>> {
+    let Some(position) = args.iter().position(|arg| arg == name) else {
+        return Ok(None);
+    };
+    if position + 1 >= args.len() {
+        return Err(Error::Validation(format!("{name} needs a value")));
+    }
+    let value = args.remove(position + 1);
+    args.remove(position);
+    Ok(Some(value))
+}
+
+fn single_positional(args: Vec<String>, usage: &str) -> Result<String> {
+    match <[String; 1]>::try_from(args) {
+        Ok([arg]) => Ok(arg),
+        Err(_) => Err(Error::Validation(format!("expected: {usage}"))),
+    }
+}
+
+fn parse_number(arg: &str, name: &str) -> Result<usize> {
+    arg.parse().map_err(|_| Error::Validation(format!("{name} expects a number, got '{arg}'")))
+}
+
+fn start_console(vram_available_mbs: usize, read_only: bool, calibration: &Calibration) -> Result<()> {
+    let mut config = SystemConfig::new();
+    // Cost columns show the calibrated level costs
+    calibration.apply(config.settings_mut());
+    let capacity = settings_string_capacity(config.settings());
+    let mut status = String::new();
+    let mut card = match VideoCardDescription::load() {
+        Ok(card) => card,
+        Err(err) => {
+            status = format!("Couldn't read the installed video card name: {err}");
+            VideoCardDescription::default()
+        }
+    };
+    // The card row sits below the settings
+    let card_row = config.settings().len();
 
     let mut index = 0;
     let mut cycle_settings = true;
-    let mut vram_used = MIN_VRAM as f64;
+    let mut confirm_write = false;
+    // Raising a setting over budget lowers others instead, which can be undone together
+    let mut budget_lock = false;
+    let mut locked: Vec<String> = vec![];
+    let mut undo: Option<Vec<tune::Lowered>> = None;
+    // Width and height the ratio was locked at
+    let mut aspect_lock: Option<(usize, usize)> = None;
+    // Digits typed into the selected numeric row and why the last attempt was rejected
+    let mut entry: Option<String> = None;
+    let mut entry_error = String::new();
+    // FPS costs fitted for the card they were loaded for
+    let mut perf_costs: (Option<String>, Option<PerfCosts>) = (None, None);
     while cycle_settings {
+        if perf_costs.0 != card.name {
+            let loaded = card.name.as_deref().and_then(perf::load);
+            perf_costs = match loaded.transpose() {
+                Ok(costs) => (card.name.clone(), costs),
+                Err(err) => {
+                    status = format!("Ignoring the perf costs: {err}");
+                    (card.name.clone(), None)
+                }
+            };
+        }
+        let vram_used = calibration.estimate(config.settings());
+        let megapixels = vram::render_megapixels(config.settings());
         let mut format = String::with_capacity(capacity);
-        format.push_str(&format!("==== VRAM USAGE {vram_used:.0} / {vram_available_mbs} ====\n"));
+        format.push_str(&format!("==== VRAM USAGE {vram_used:.0} / {vram_available_mbs} ===="));
+        if let Some(costs) = &perf_costs.1 {
+            format.push_str(&format!(" ~{:.0} FPS", costs.estimate_fps(config.settings())));
+        }
+        if budget_lock {
+            format.push_str(" BUDGET LOCK");
+        }
+        format.push('\n');
 
-        for (i, setting) in settings.iter().enumerate() {
+        let violations = rules::check(config.settings(), config.defaults());
+        for (i, setting) in config.settings().iter().enumerate() {
+            if !is_shown(config.settings(), i) {
+                continue;
+            }
             if i == index {
                 format.push_str(" > ");
             } else {
@@ -46,170 +741,429 @@ fn start_console(vram_available_mbs: usize) {
             let nice_name_length = setting.nice_name.len();
             format.push_str(&setting.nice_name);
             pad_with_spaces(&mut format, PADDING - nice_name_length);
-            append_setting_type(&mut format, &setting.setting_type);
+            match &entry {
+                Some(typed) if i == index => {
+                    format.push_str(&format!("[{typed}_]  {entry_error}\n"));
+                }
+                _ => {
+                    settings::append_setting_type(&mut format, &setting.setting_type);
+                    append_costs(&mut format, setting, megapixels, perf_costs.1.as_ref());
+                }
+            }
+            if locked.contains(&setting.tag) {
+                format.pop();
+                format.push_str("  [locked]\n");
+            }
+            if !settings::is_active(config.settings(), setting) {
+                let parent = settings::depends_on(&setting.tag).unwrap_or_default();
+                let parent = config.setting(parent).map_or(parent, |parent| parent.nice_name.as_str());
+                format.pop();
+                format.push_str(&format!("  (needs {parent})\n"));
+            } else if let Some(violation) = violations.iter().find(|violation| violation.tag == setting.tag) {
+                format.pop();
+                format.push_str(&format!("  (! {})\n", violation.note));
+            } else if let Some(note) = row_note(config.settings(), setting) {
+                format.pop();
+                format.push_str(&format!("  ({note})\n"));
+            }
+            // Enter types on numeric rows, so say where writing went
+            if i == index && entry.is_none() && is_numeric(config.settings(), i) {
+                format.push_str("   [Enter/=/0-9] type a value, [Enter] on any other row writes the file\n");
+            }
+            if let SettingType::Slider(_, _, false) = setting.setting_type {
+                append_resolution(&mut format, config.settings(), aspect_lock, is_slider(config.settings(), index));
+            }
         }
+        append_video_card(&mut format, &card, index == card_row);
+        format.push_str(&status);
         clear_console();
         println!("{format}");
 
         let key = read_key();
+        // Every key's changes go through the same budget check afterwards
+        let before = config.settings().to_vec();
+        if let Some(typed) = entry.as_mut() {
+            entry_error.clear();
+            match key {
+                KeyCode::Error => {
+                    return Err(Error::Terminal("couldn't read a key from the console".into()));
+                }
+                KeyCode::Char(chr) if chr.is_ascii_digit() => typed.push(chr),
+                KeyCode::Char('.') if is_refresh_rate(config.settings(), index) => typed.push('.'),
+                KeyCode::Backspace => {
+                    typed.pop();
+                }
+                KeyCode::Enter | KeyCode::Char('=') => {
+                    match validation::set_typed(&mut config.settings_mut()[index], typed) {
+                        Ok(()) => {
+                            entry = None;
+                            status.clear();
+                            if let Some(ratio) = aspect_lock {
+                                keep_aspect(config.settings_mut(), index, ratio, &mut status);
+                            }
+                        }
+                        Err(Error::Validation(message)) => entry_error = message,
+                        Err(err) => entry_error = err.to_string(),
+                    }
+                }
+                // Anything else cancels
+                _ => entry = None,
+            }
+            settle_changes(config.settings_mut(), &before, &tune_options(vram_available_mbs, &locked, calibration), budget_lock, &mut undo, &mut status);
+            continue;
+        }
+        let pending_confirmation = confirm_write;
+        confirm_write = false;
+        status.clear();
         match key {
+            KeyCode::Error => {
+                return Err(Error::Terminal("couldn't read a key from the console".into()));
+            }
             KeyCode::Backspace => {
                 cycle_settings = false;
             }
             KeyCode::ArrowUp | KeyCode::Char('w') | KeyCode::Char('W') => {
-                if index > 0 {
-                    index -= 1;
+                if let Some(previous) = (0..index).rev().find(|i| is_shown(config.settings(), *i)) {
+                    index = previous;
                 }
             }
-            KeyCode::ArrowDown | KeyCode::Char('s') | KeyCode::Char('S') => {
-                if index + 1 < settings.len() {
-                    index += 1;
-                }
+            KeyCode::ArrowDown | KeyCode::Char('s') | KeyCode::Char('S') if index < card_row => {
+                index = (index + 1..card_row).find(|i| is_shown(config.settings(), *i)).unwrap_or(card_row);
             }
-            KeyCode::ArrowLeft | KeyCode::Char('a') | KeyCode::Char('A') => {
-                let Slider(width, _, _) = settings[width_index].setting_type else {
-                    panic!("Width not found?");
+            KeyCode::Char('f') | KeyCode::Char('F') => {
+                let options = tune_options(vram_available_mbs, &locked, calibration);
+                status = match tune::tune(config.settings_mut(), &options) {
+                    Ok(estimate) => format!("Fitted to budget: {estimate:.0} / {vram_available_mbs} MB"),
+                    Err(err) => err.to_string(),
                 };
-                let Slider(height, _, _) = settings[height_index].setting_type else {
-                    panic!("Height not found?");
+            }
+            KeyCode::Enter | KeyCode::Char('=') if is_numeric(config.settings(), index) => {
+                entry = Some(String::new());
+            }
+            KeyCode::Char(chr) if chr.is_ascii_digit() && is_numeric(config.settings(), index) => {
+                entry = Some(chr.to_string());
+            }
+            KeyCode::Char('k') | KeyCode::Char('K') => {
+                aspect_lock = match aspect_lock {
+                    Some(_) => None,
+                    None => Some(config.screen_resolution()),
                 };
-                match &mut settings[index].setting_type {
-                    Level(selected_index, _, vram_levels) => {
-                        if *selected_index > 0 {
-                            *selected_index -= 1;
-                            vram_used -= vram_levels[*selected_index] as f64;
-                        }
-                    }
-                    OnOff(enabled) => {
-                        *enabled = !*enabled;
-                    }
-                    Multiplier(value, _) => {
-                        if *value <= 2 {
-                            *value = 0;
-                        } else {
-                            *value /= 2;
-                        }
+            }
+            KeyCode::Char('m') | KeyCode::Char('M') | KeyCode::Char('n') | KeyCode::Char('N') if is_slider(config.settings(), index) => {
+                let forward = matches!(key, KeyCode::Char('m') | KeyCode::Char('M'));
+                let (width, height) = next_standard_mode(config.screen_resolution(), aspect_lock, forward);
+                if let Err(err) = resolution::set_resolution(config.settings_mut(), width, height) {
+                    status = err.to_string();
+                }
+            }
+            KeyCode::Space if is_slider(config.settings(), index) => {
+                let typed = read_typed_line("Type a resolution like 2560x1440 or a mode like 1440p: ");
+                let result = resolution::parse(&typed)
+                    .and_then(|(width, height)| resolution::set_resolution(config.settings_mut(), width, height));
+                match result {
+                    Ok(()) if aspect_lock.is_some() => aspect_lock = Some(config.screen_resolution()),
+                    Ok(()) => {}
+                    Err(err) => status = err.to_string(),
+                }
+            }
+            KeyCode::Char('b') | KeyCode::Char('B') => {
+                budget_lock = !budget_lock;
+            }
+            KeyCode::Char('l') | KeyCode::Char('L') if index < card_row => {
+                let tag = &config.settings()[index].tag;
+                match locked.iter().position(|existing| existing == tag) {
+                    Some(position) => {
+                        locked.remove(position);
                     }
-                    OnHalfOff(value) => {
-                        // OFF ON HALF
-                        if *value == 0 {
-                            *value = 2;
-                        } else if *value == 1 {
-                            *value = 0;
-                        } else if *value == 2 {
-                            *value = 1;
-                        }
+                    None => locked.push(tag.clone()),
+                }
+            }
+            KeyCode::Char('u') | KeyCode::Char('U') => match undo.take() {
+                Some(lowered) => {
+                    for entry in lowered {
+                        config.settings_mut()[entry.index].setting_type = entry.before;
                     }
-                    Slider(value, jump, horizontal) => {
-                        let jmp = *jump;
-                        if *value < jmp * 2 {
-                            continue;
-                        }
-                        *value -= jmp;
-                        let pixel_diff = match *horizontal {
-                            true => (height * jmp) as f64,
-                            false => (width * jmp) as f64
-                        };
-                        let mb_diff = pixel_diff / settings::PIXELS_PER_1MB_VRAM;
-                        vram_used -= mb_diff;
+                    status = "Undone".into();
+                    // Restoring isn't a change to fit again
+                    continue;
+                }
+                None => status = "Nothing to undo".into(),
+            },
+            KeyCode::Char('c') | KeyCode::Char('C') => {
+                status = match share::encode(config.settings()) {
+                    Ok(code) => format!("Settings code: {code}"),
+                    Err(err) => err.to_string(),
+                };
+            }
+            KeyCode::Char('v') | KeyCode::Char('V') => {
+                let code = read_typed_line("Paste a settings code: ");
+                if let Err(err) = share::decode(code.trim(), config.settings_mut()) {
+                    status = err.to_string();
+                }
+            }
+            KeyCode::Space if index == card_row => {
+                card.set_typed(&read_typed_line("Type video card name (empty clears it): "));
+            }
+            KeyCode::ArrowLeft | KeyCode::Char('a') | KeyCode::Char('A') if index == card_row => {
+                card.cycle(false);
+            }
+            KeyCode::ArrowRight | KeyCode::Char('d') | KeyCode::Char('D') if index == card_row => {
+                card.cycle(true);
+            }
+            KeyCode::ArrowLeft | KeyCode::Char('a') | KeyCode::Char('A') | KeyCode::ArrowRight | KeyCode::Char('d') | KeyCode::Char('D')
+                if config.settings()[index].tag == "API" =>
+            {
+                let forward = matches!(key, KeyCode::ArrowRight | KeyCode::Char('d') | KeyCode::Char('D'));
+                let setting_type = &mut config.settings_mut()[index].setting_type;
+                let switched = if forward { setting_type.increase() } else { setting_type.decrease() };
+                if switched {
+                    let changed: Vec<String> = settings::apply_api_defaults(config.settings_mut()).into_iter()
+                        .map(|changed| {
+                            let setting = &config.settings()[changed];
+                            format!("{} {}", setting.nice_name, value_text(&setting.setting_type))
+                        })
+                        .collect();
+                    if !changed.is_empty() {
+                        status = format!("Recommended for {}: {}", value_text(&config.settings()[index].setting_type), changed.join(", "));
                     }
                 }
             }
+            KeyCode::ArrowLeft | KeyCode::Char('a') | KeyCode::Char('A') => {
+                config.settings_mut()[index].setting_type.decrease();
+                if let Some(ratio) = aspect_lock {
+                    keep_aspect(config.settings_mut(), index, ratio, &mut status);
+                }
+            }
             KeyCode::ArrowRight | KeyCode::Char('d') | KeyCode::Char('D') => {
-                let Slider(width, _, _) = settings[width_index].setting_type else {
-                    panic!("Width not found?");
-                };
-                let Slider(height, _, _) = settings[height_index].setting_type else {
-                    panic!("Height not found?");
-                };
-                match &mut settings[index].setting_type {
-                    Level(selected_index, selectable, vram_levels) => {
-                        if *selected_index + 1 < selectable.len() {
-                            *selected_index += 1;
-                            vram_used += vram_levels[*selected_index - 1] as f64;
-                        }
-                    }
-                    OnOff(enabled) => {
-                        *enabled = !*enabled;
-                    }
-                    Multiplier(value, max_factor) => {
-                        if *value == 0 {
-                            *value = 2;
-                        } else if value < max_factor && *value >= 2 {
-                            *value *= 2;
-                        }
-                    }
-                    OnHalfOff(value) => {
-                        // OFF ON HALF
-                        if *value == 0 {
-                            *value = 1;
-                        } else if *value == 1 {
-                            *value = 2;
-                        } else if *value == 2 {
-                            *value = 0;
-                        }
-                    }
-                    Slider(value, jump, horizontal) => {
-                        let jmp = *jump;
-                        *value += jmp;
-                        let pixel_diff = match *horizontal {
-                            true => (height * jmp) as f64,
-                            false => (width * jmp) as f64
-                        };
-                        let mb_diff = pixel_diff / settings::PIXELS_PER_1MB_VRAM;
-                        vram_used += mb_diff;
-                        //add vram reduction logic
-                    }
+                config.settings_mut()[index].setting_type.increase();
+                if let Some(ratio) = aspect_lock {
+                    keep_aspect(config.settings_mut(), index, ratio, &mut status);
                 }
             }
             KeyCode::Enter => {
+                let card_name = match card.require_name() {
+                    Ok(card_name) => card_name,
+                    Err(err) => {
+                        status = format!("Refusing to write. {err}");
+                        continue;
+                    }
+                };
+                match card.check() {
+                    CardCheck::Mismatch(current) if !pending_confirmation => {
+                        status.push_str("!!!!!!!! VIDEO CARD MISMATCH !!!!!!!!\n");
+                        status.push_str(&format!("Installed system.xml has: {current}\n"));
+                        status.push_str("The game will reset this config. Press Enter again to write anyway.");
+                        confirm_write = true;
+                        continue;
+                    }
+                    _ => {}
+                }
                 println!("Committing write");
-                settings::commit_xml_write(settings);
-                break;
+                match write_config(&config.clone().video_card(card_name), Path::new(OUTPUT_FILE), read_only) {
+                    Ok(()) => break,
+                    Err(err) => status = format!("Write failed: {err}"),
+                }
             }
             _ => {}
         }
+        settle_changes(config.settings_mut(), &before, &tune_options(vram_available_mbs, &locked, calibration), budget_lock, &mut undo, &mut status);
     }
+    Ok(())
 }
 
-const DEFAULT_SETTINGS_PATH: &str = "C:/Users/Name/Documents/Rockstar Games/Red Dead Redemption 2/Settings/system.xml";
-
-fn append_setting_type(format: &mut String, setting_type: &SettingType) {
-    match setting_type {
-        Level(selected_index, selectable, _) => {
-            let option = &selectable[*selected_index];
-            format.push_str(&option.nice_name);
+// Any change replaces the last undo step. With the budget lock on, a change that pushed the estimate
+// over budget lowers other settings instead, the change and what was lowered are undone together
+fn settle_changes(settings: &mut [Setting], before: &[Setting], options: &TuneOptions, budget_lock: bool,
+    undo: &mut Option<Vec<tune::Lowered>>, status: &mut String) {
+    let changed: Vec<usize> = (0..settings.len())
+        .filter(|i| xml::setting_text(&settings[*i]) != xml::setting_text(&before[*i]))
+        .collect();
+    let Some(&first) = changed.first() else {
+        return;
+    };
+    *undo = None;
+    let estimate = options.calibration.estimate(settings);
+    if !budget_lock || estimate <= options.vram_budget as f64 || estimate <= options.calibration.estimate(before) {
+        return;
+    }
+    // What the key changed stays as it was set
+    let options = changed.iter().fold(options.clone(), |options, i| options.lock(&settings[*i].tag));
+    if !status.is_empty() {
+        status.push('\n');
+    }
+    match tune::lower_to_fit(settings, first, &options) {
+        Ok(lowered) => {
+            let changes: Vec<String> = lowered.iter().map(|entry| {
+                let setting = &settings[entry.index];
+                format!("{} {} -> {}", setting.nice_name, value_text(&entry.before), value_text(&setting.setting_type))
+            }).collect();
+            status.push_str(&format!("Lowered to fit: {}\n[U] undo", changes.join(", ")));
+            let mut step = lowered;
+            step.extend(changed.iter().map(|i| tune::Lowered { index: *i, before: before[*i].setting_type.clone() }));
+            *undo = Some(step);
         }
-        OnOff(enabled) => {
-            if *enabled {
-                format.push_str("ON");
-            } else {
-                format.push_str("OFF");
-            }
+        Err(err) => {
+            settings.clone_from_slice(before);
+            status.push_str(&err.to_string());
         }
-        Multiplier(value, _) => {
-            if *value == 0 {
-                format.push_str("OFF");
-            } else {
-                format.push('X');
-                format.push_str(&value.to_string());
-            }
-        }
-        OnHalfOff(value) => {
-            if *value == 0 {
-                format.push_str("OFF");
-            } else if *value == 1 {
-                format.push_str("ON");
-            } else if *value == 2 {
-                format.push_str("HALF");
-            }
+    }
+}
+
+// VRAM and FPS cost columns after the value, when there's a cost to show
+fn append_costs(format: &mut String, setting: &Setting, megapixels: f64, perf_costs: Option<&PerfCosts>) {
+    let vram_cost = vram::setting_vram(setting, megapixels);
+    let fps_cost = perf_costs.and_then(|costs| costs.setting_cost(setting));
+    if vram_cost < 0.5 && fps_cost.is_none() {
+        return;
+    }
+    format.pop();
+    let line_length = format.len() - format.rfind('\n').map_or(0, |i| i + 1);
+    pad_with_spaces(format, (PADDING + VALUE_WIDTH).saturating_sub(line_length));
+    if vram_cost >= 0.5 {
+        format.push_str(&format!("{vram_cost:>6.0} MB"));
+    } else {
+        pad_with_spaces(format, 9);
+    }
+    if let Some(fps_cost) = fps_cost {
+        format.push_str(&format!("{:>8.1} fps", -fps_cost));
+    }
+    format.push('\n');
+}
+
+fn is_numeric(settings: &[Setting], index: usize) -> bool {
+    let setting_type = settings.get(index).map(|setting| &setting.setting_type);
+    matches!(setting_type, Some(SettingType::Slider(..) | SettingType::Multiplier(..) | SettingType::OnHalfOff(..)
+        | SettingType::Indexed(..) | SettingType::RefreshRate(..) | SettingType::Range(..)))
+}
+
+// Which API an option is for and how the API runs here
+fn row_note(settings: &[Setting], setting: &Setting) -> Option<String> {
+    if setting.tag == "API" {
+        return settings::api_note(settings).map(String::from);
+    }
+    settings::RULES.iter().find_map(|rule| match rule {
+        Rule::OnlyWith(tag, other, value) if *tag == setting.tag => {
+            let other = settings.iter().find(|setting| setting.tag == *other)?;
+            let SettingType::Level(_, selectables, _) = &other.setting_type else {
+                return None;
+            };
+            let option = selectables.iter().find(|option| option.config_name == *value)?;
+            Some(format!("{} only", option.nice_name))
         }
-        Slider(value, _, _) => {
-            format.push_str(&value.to_string());
+        _ => None,
+    })
+}
+
+// Numbers of a group that's off are hidden, toggles stay visible and are marked
+fn is_shown(settings: &[Setting], index: usize) -> bool {
+    let Some(setting) = settings.get(index) else {
+        return true;
+    };
+    !matches!(setting.setting_type, SettingType::Range(..)) || settings::is_active(settings, setting)
+}
+
+fn is_refresh_rate(settings: &[Setting], index: usize) -> bool {
+    matches!(settings.get(index).map(|setting| &setting.setting_type), Some(SettingType::RefreshRate(..)))
+}
+
+fn is_slider(settings: &[Setting], index: usize) -> bool {
+    matches!(settings.get(index).map(|setting| &setting.setting_type), Some(SettingType::Slider(..)))
+}
+
+// After one slider moved, moves the other so the locked ratio holds
+fn keep_aspect(settings: &mut [Setting], index: usize, ratio: (usize, usize), status: &mut String) {
+    let SettingType::Slider(value, jump, horizontal) = settings[index].setting_type else {
+        return;
+    };
+    let (width, height) = if horizontal {
+        (value, resolution::height_for_width(value, ratio, jump))
+    } else {
+        (resolution::width_for_height(value, ratio, jump), value)
+    };
+    if let Err(err) = resolution::set_resolution(settings, width, height) {
+        *status = err.to_string();
+    }
+}
+
+// With the aspect locked only modes of the same ratio are offered
+fn next_standard_mode(current: (usize, usize), aspect_lock: Option<(usize, usize)>, forward: bool) -> (usize, usize) {
+    let mut candidate = current;
+    for _ in 0..resolution::MODES.len() {
+        let mode = resolution::next_mode(candidate.0, candidate.1, forward);
+        candidate = (mode.width, mode.height);
+        let matches_lock = aspect_lock.is_none_or(|(width, height)| {
+            resolution::aspect_ratio(width, height) == resolution::aspect_ratio(mode.width, mode.height)
+        });
+        if matches_lock {
+            return candidate;
         }
     }
+    current
+}
+
+fn append_resolution(format: &mut String, settings: &[Setting], aspect_lock: Option<(usize, usize)>, selected: bool) {
+    let (width, height) = vram::resolution(settings);
+    let mode = resolution::mode_name(width, height).unwrap_or("custom");
+    format.push_str(&format!("   Resolution {width}x{height} ({mode}, {})", resolution::aspect_ratio(width, height)));
+    if let Some((width, height)) = aspect_lock {
+        format.push_str(&format!(", locked to {}", resolution::aspect_ratio(width, height)));
+    }
+    let render = vram::render_resolution(settings);
+    if render != (width, height) {
+        format.push_str(&format!(", renders at {}x{}", render.0, render.1));
+    }
+    format.push('\n');
+    if selected {
+        format.push_str("   [M/N] next/previous standard mode, [Space] type a resolution, [K] lock aspect ratio\n");
+    }
+}
+
+fn value_text(setting_type: &SettingType) -> String {
+    let mut text = String::new();
+    settings::append_setting_type(&mut text, setting_type);
+    text.trim_end().to_string()
+}
 
+fn tune_options(vram_budget: usize, locked: &[String], calibration: &Calibration) -> TuneOptions {
+    let options = TuneOptions::new(vram_budget).calibration(calibration.clone());
+    locked.iter().fold(options, |options, tag| options.lock(tag))
+}
+
+fn read_typed_line(prompt: &str) -> String {
+    println!("{prompt}");
+    let mut line = String::new();
+    if std::io::stdin().read_line(&mut line).is_err() {
+        return String::new();
+    }
+    line
+}
+
+fn append_video_card(format: &mut String, card: &VideoCardDescription, selected: bool) {
+    format.push('\n');
+    if selected {
+        format.push_str(" > ");
+    } else {
+        format.push_str("   ");
+    }
+    let nice_name = "Video Card";
+    format.push_str(nice_name);
+    pad_with_spaces(format, PADDING - nice_name.len());
+    match &card.name {
+        Some(name) => format.push_str(name),
+        None => format.push_str("<MISSING>"),
+    }
     format.push('\n');
+    match card.check() {
+        CardCheck::Missing => format.push_str("   WARNING: no video card name, the game would reset this config\n"),
+        CardCheck::Mismatch(current) => {
+            format.push_str(&format!("   WARNING: differs from installed system.xml ({current})\n"));
+        }
+        CardCheck::NoCurrentFile => format.push_str("   No installed system.xml found to compare against\n"),
+        CardCheck::Matching => {}
+    }
+    if selected {
+        format.push_str("   [A/D] cycle known names, [Space] type a name\n");
+    }
 }
 
 fn pad_with_spaces(str: &mut String, spaces: usize) {
@@ -228,8 +1182,7 @@ fn settings_string_capacity(settings: &[Setting]) -> usize {
 
 #[allow(dead_code)]
 fn key_testing() {
-    let mut looping = true;
-    while looping {
+    loop {
         let key = read_key();
         match key {
             KeyCode::Char(chr) => println!("{chr}"),
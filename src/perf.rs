@@ -0,0 +1,298 @@
+//! FPS cost of settings, fitted per video card from benchmark results.
+//!
+//! Each benchmark whose settings are known is a sample: the average FPS and the settings it ran
+//! with. Least squares over the samples of one card gives the FPS lost by every step a setting
+//! goes up, which is written to `perf/<card>.toml`:
+//!
+//! ```toml
+//! gpu = "NVIDIA GeForce RTX 3070"
+//! base_fps = 142.5
+//! fps_per_megapixel = 9.8
+//!
+//! [msaa]
+//! 2 = 6.1
+//! 4 = 8.4
+//! ```
+//!
+//! Steps are named by the value they go to, as it's written in `system.xml`.
+//! Only steps the samples varied are written, the others aren't known.
+
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+use crate::bench::Benchmark;
+use crate::calibration;
+use crate::config::SystemConfig;
+use crate::error::{Error, Result};
+use crate::export::{self, json_string, toml_key};
+use crate::settings::Setting;
+use crate::settings::SettingType::{Level, Multiplier, OnOff};
+use crate::video_card::VideoCard;
+use crate::{vram, xml};
+
+pub const PERF_DIR: &str = "perf";
+
+const GPU_KEY: &str = "gpu";
+const BASE_FPS_KEY: &str = "base_fps";
+const FPS_PER_MEGAPIXEL_KEY: &str = "fps_per_megapixel";
+const RIDGE: f64 = 1e-3;
+
+/// Average FPS of a benchmark run and the settings it ran with.
+#[derive(Clone)]
+pub struct Sample {
+    pub fps: f64,
+    pub settings: Vec<Setting>,
+}
+
+/// Fitted FPS costs of one video card.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PerfCosts {
+    pub gpu: VideoCard,
+    /// FPS with every setting at its lowest, before the resolution cost.
+    pub base_fps: f64,
+    pub fps_per_megapixel: f64,
+    /// FPS lost by each known step, by tag and the value stepped to.
+    pub steps: Vec<(String, Vec<(String, f64)>)>,
+}
+
+impl PerfCosts {
+    pub fn read_file(path: &Path) -> Result<Self> {
+        Self::parse(&xml::read_to_string(path)?)
+    }
+
+    pub fn parse(content: &str) -> Result<Self> {
+        let state = export::parse_toml(content)?;
+        let mut costs = Self { gpu: String::new(), base_fps: 0.0, fps_per_megapixel: 0.0, steps: vec![] };
+        for (key, value) in &state.top {
+            match key.as_str() {
+                GPU_KEY => costs.gpu = value.clone(),
+                BASE_FPS_KEY => costs.base_fps = parse_number(key, value)?,
+                FPS_PER_MEGAPIXEL_KEY => costs.fps_per_megapixel = parse_number(key, value)?,
+                _ => return Err(invalid(&format!("unknown key '{key}'"))),
+            }
+        }
+        if costs.gpu.is_empty() {
+            return Err(invalid(&format!("missing {GPU_KEY}")));
+        }
+        let catalog = crate::settings::get_settings();
+        for (tag, table) in &state.sections {
+            let setting = catalog.iter().find(|setting| &setting.tag == tag).ok_or_else(|| invalid(&format!("unknown setting {tag}")))?;
+            let Some((options, _)) = steps_of(setting) else {
+                return Err(invalid(&format!("{tag} has no steps")));
+            };
+            let mut steps = vec![];
+            for (option, value) in table {
+                if !options.contains(option) {
+                    return Err(invalid(&format!("{tag} has no step to {option}")));
+                }
+                steps.push((option.clone(), parse_number(option, value)?));
+            }
+            costs.steps.push((tag.clone(), steps));
+        }
+        Ok(costs)
+    }
+
+    pub fn to_toml(&self, comment: &str) -> String {
+        let mut output = String::new();
+        for line in comment.lines() {
+            let _ = writeln!(output, "# {line}");
+        }
+        let _ = writeln!(output, "{GPU_KEY} = {}", json_string(&self.gpu));
+        let _ = writeln!(output, "{BASE_FPS_KEY} = {:.2}", self.base_fps);
+        let _ = writeln!(output, "{FPS_PER_MEGAPIXEL_KEY} = {:.2}", self.fps_per_megapixel);
+        for (tag, steps) in &self.steps {
+            let _ = writeln!(output, "\n[{}]", toml_key(tag));
+            for (option, cost) in steps {
+                let _ = writeln!(output, "{} = {cost:.2}", toml_key(option));
+            }
+        }
+        output
+    }
+
+    /// FPS lost by the selected value over the lowest one, `None` unless every step up to it is known.
+    pub fn setting_cost(&self, setting: &Setting) -> Option<f64> {
+        let (options, selected) = steps_of(setting)?;
+        let (_, known) = self.steps.iter().find(|(tag, _)| *tag == setting.tag)?;
+        options.iter().take(selected)
+            .map(|option| known.iter().find(|(name, _)| name == option).map(|(_, cost)| *cost))
+            .sum()
+    }
+
+    /// Predicted average FPS, settings whose cost isn't known count as free.
+    pub fn estimate_fps(&self, settings: &[Setting]) -> f64 {
+        let costs: f64 = settings.iter().filter_map(|setting| self.setting_cost(setting)).sum();
+        self.base_fps - self.fps_per_megapixel * vram::render_megapixels(settings) - costs
+    }
+}
+
+fn invalid(message: &str) -> Error {
+    Error::Validation(format!("perf costs: {message}"))
+}
+
+fn parse_number(key: &str, value: &str) -> Result<f64> {
+    value.parse().map_err(|_| invalid(&format!("{key} expects a number, got '{value}'")))
+}
+
+// Values above the lowest one as written in the file, and how many of them the selected value includes
+fn steps_of(setting: &Setting) -> Option<(Vec<String>, usize)> {
+    match &setting.setting_type {
+        Level(selected_index, selectables, _) => {
+            let options = selectables.iter().skip(1).map(|selectable| selectable.config_name.clone()).collect();
+            Some((options, *selected_index))
+        }
+        Multiplier(value, max_factor) => {
+            let options = (1..=max_factor.trailing_zeros()).map(|power| (1usize << power).to_string()).collect();
+            let selected = if *value == 0 { 0 } else { value.trailing_zeros() as usize };
+            Some((options, selected))
+        }
+        OnOff(enabled) => Some((vec!["true".into()], usize::from(*enabled))),
+        _ => None,
+    }
+}
+
+/// File the costs of a card are kept in, `perf/nvidia-geforce-rtx-3070.toml`.
+pub fn costs_path(gpu: &str) -> PathBuf {
+    let mut name = String::new();
+    for chr in gpu.chars() {
+        if chr.is_ascii_alphanumeric() {
+            name.push(chr.to_ascii_lowercase());
+        } else if !name.is_empty() && !name.ends_with('-') {
+            name.push('-');
+        }
+    }
+    Path::new(PERF_DIR).join(format!("{}.toml", name.trim_end_matches('-')))
+}
+
+/// Costs fitted for the card, `None` when there are none yet.
+pub fn load(gpu: &str) -> Option<Result<PerfCosts>> {
+    let path = costs_path(gpu);
+    path.exists().then(|| PerfCosts::read_file(&path))
+}
+
+/// Samples of the benchmarks whose settings are known, grouped by video card in order of appearance.
+pub fn samples(benchmarks: &[Benchmark], configs: &[(PathBuf, std::time::SystemTime)]) -> Vec<(VideoCard, Vec<Sample>)> {
+    let mut groups: Vec<(VideoCard, Vec<Sample>)> = vec![];
+    for benchmark in benchmarks {
+        let Some(config) = benchmark.settings_path(configs).and_then(|path| SystemConfig::read_file(&path).ok()) else {
+            continue;
+        };
+        let Some(gpu) = config.video_card_name() else {
+            continue;
+        };
+        let sample = Sample { fps: benchmark.avg_fps(), settings: config.settings().to_vec() };
+        match groups.iter_mut().find(|(name, _)| name == gpu) {
+            Some((_, samples)) => samples.push(sample),
+            None => groups.push((gpu.into(), vec![sample])),
+        }
+    }
+    groups
+}
+
+/// Fitted costs together with how well they explain the samples.
+pub struct Fit {
+    pub costs: PerfCosts,
+    /// Measured and predicted FPS of every sample.
+    pub residuals: Vec<(f64, f64)>,
+    pub rmse: f64,
+}
+
+/// Least squares fit of the base FPS, the FPS per megapixel and every step the samples vary.
+pub fn fit(gpu: &str, samples: &[Sample]) -> Result<Fit> {
+    if samples.len() < 2 {
+        return Err(Error::Validation(format!("at least 2 benchmarks with known settings are needed for {gpu}")));
+    }
+    let catalog = crate::settings::get_settings();
+    // Setting index and step of every step cost, after the base and the resolution
+    let mut parameters: Vec<Option<(usize, usize)>> = vec![None, None];
+    for (index, setting) in catalog.iter().enumerate() {
+        if let Some((options, _)) = steps_of(setting) {
+            parameters.extend((0..options.len()).map(|step| Some((index, step))));
+        }
+    }
+    let rows: Vec<Vec<f64>> = samples.iter().map(|sample| features(&parameters, &sample.settings)).collect();
+    // Only what the samples vary can be told apart from the base
+    let varied: Vec<bool> = (0..parameters.len())
+        .map(|i| i == 0 || rows.iter().any(|row| row[i] != rows[0][i]))
+        .collect();
+    let mut free = varied.clone();
+    let mut solution;
+    loop {
+        let indices: Vec<usize> = (0..parameters.len()).filter(|i| free[*i]).collect();
+        let size = indices.len();
+        let mut matrix = vec![vec![0.0; size + 1]; size];
+        for (row, sample) in rows.iter().zip(samples) {
+            for (a, &i) in indices.iter().enumerate() {
+                for (b, &j) in indices.iter().enumerate() {
+                    matrix[a][b] += row[i] * row[j];
+                }
+                matrix[a][size] += row[i] * sample.fps;
+            }
+        }
+        // A small ridge towards 0 settles steps that always change together, the base is left alone
+        for (a, row) in matrix.iter_mut().enumerate().skip(1) {
+            row[a] += RIDGE;
+        }
+        solution = vec![0.0; parameters.len()];
+        for (i, value) in indices.into_iter().zip(calibration::solve(matrix)?) {
+            solution[i] = value;
+        }
+        // Features are negated, so a negative cost is a step that gained FPS: noise, pin it at 0
+        let negative: Vec<usize> = (1..parameters.len()).filter(|i| free[*i] && solution[*i] < 0.0).collect();
+        if negative.is_empty() {
+            break;
+        }
+        for i in negative {
+            free[i] = false;
+            solution[i] = 0.0;
+        }
+    }
+
+    let mut costs = PerfCosts { gpu: gpu.into(), base_fps: solution[0], fps_per_megapixel: solution[1], steps: vec![] };
+    for (index, setting) in catalog.iter().enumerate() {
+        let Some((options, _)) = steps_of(setting) else {
+            continue;
+        };
+        let known: Vec<(String, f64)> = parameters.iter().zip(&solution).enumerate()
+            .filter(|(i, _)| varied[*i])
+            .filter_map(|(_, (parameter, cost))| match parameter {
+                Some((setting_index, step)) if *setting_index == index => Some((options[*step].clone(), *cost)),
+                _ => None,
+            })
+            .collect();
+        if !known.is_empty() {
+            costs.steps.push((setting.tag.clone(), known));
+        }
+    }
+
+    let residuals: Vec<(f64, f64)> = samples.iter().map(|sample| (sample.fps, costs.estimate_fps(&sample.settings))).collect();
+    let squared: f64 = residuals.iter().map(|(measured, predicted)| (measured - predicted).powi(2)).sum();
+    let rmse = (squared / residuals.len() as f64).sqrt();
+    Ok(Fit { costs, residuals, rmse })
+}
+
+// Constant, then minus the render megapixels and minus 1 for every step the selected value includes
+fn features(parameters: &[Option<(usize, usize)>], settings: &[Setting]) -> Vec<f64> {
+    let mut row = vec![1.0, -vram::render_megapixels(settings)];
+    for (index, step) in parameters[2..].iter().flatten() {
+        let included = steps_of(&settings[*index]).is_some_and(|(_, selected)| *step < selected);
+        row.push(if included { -1.0 } else { 0.0 });
+    }
+    row
+}
+
+/// Plain-text list of the fitted costs and the error per sample.
+pub fn format_report(fit: &Fit) -> String {
+    let mut output = String::new();
+    let costs = &fit.costs;
+    let _ = writeln!(output, "{}: {} benchmarks, RMSE {:.1} fps", costs.gpu, fit.residuals.len(), fit.rmse);
+    let _ = writeln!(output, "  {:<48} {:>8.1} fps", "Base", costs.base_fps);
+    let _ = writeln!(output, "  {:<48} {:>8.1} fps", "Per megapixel", costs.fps_per_megapixel);
+    for (tag, steps) in &costs.steps {
+        for (option, cost) in steps {
+            let _ = writeln!(output, "  {:<48} {:>8.1} fps", format!("{tag} {option}"), cost);
+        }
+    }
+    for (i, (measured, predicted)) in fit.residuals.iter().enumerate() {
+        let _ = writeln!(output, "  #{:<4} measured {measured:>6.1} fps  predicted {predicted:>6.1} fps", i + 1);
+    }
+    output
+}
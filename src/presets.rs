@@ -0,0 +1,59 @@
+//! Quality presets applied on top of the catalog.
+
+use crate::settings::Setting;
+use crate::settings::SettingType::{Level, Multiplier};
+
+const QUALITY_LEVEL_PREFIX: &str = "kSettingLevel_";
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Preset {
+    Low,
+    Medium,
+    High,
+    Ultra,
+}
+
+impl Preset {
+    pub const ALL: [Preset; 4] = [Preset::Low, Preset::Medium, Preset::High, Preset::Ultra];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Preset::Low => "low",
+            Preset::Medium => "medium",
+            Preset::High => "high",
+            Preset::Ultra => "ultra",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|preset| preset.name().eq_ignore_ascii_case(name))
+    }
+
+    fn rank(&self) -> usize {
+        *self as usize
+    }
+}
+
+/// Moves every quality level and multiplier to the preset, toggles, the API and the resolution are kept.
+///
+/// Settings with fewer levels than the preset are capped at their highest level.
+pub fn apply_preset(settings: &mut [Setting], preset: Preset) {
+    for setting in settings.iter_mut() {
+        match &mut setting.setting_type {
+            Level(selected_index, selectables, _) => {
+                let is_quality = selectables.iter().all(|option| option.config_name.starts_with(QUALITY_LEVEL_PREFIX));
+                if is_quality {
+                    *selected_index = preset.rank().min(selectables.len() - 1);
+                }
+            }
+            Multiplier(value, max_factor) => {
+                // 0, 2, 4, 8... capped at the max factor
+                *value = match preset.rank() {
+                    0 => 0,
+                    rank => (1 << rank).min(*max_factor),
+                };
+            }
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,105 @@
+//! Settings report to paste into forum posts.
+//!
+//! Values are shown the way the selector shows them, see [`settings::append_setting_type`].
+
+use std::fmt::Write;
+use crate::calibration::Calibration;
+use crate::config::SystemConfig;
+use crate::settings::{self, SettingType, XMLSection};
+use crate::validation;
+
+const TEXT_PADDING: usize = 40;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ReportFormat {
+    Markdown,
+    Text,
+}
+
+/// Summary followed by one table per section, the GPU name is only included when asked for.
+/// Invalid values are shown as such and listed at the end.
+pub fn render(config: &SystemConfig, format: ReportFormat, include_gpu: bool, calibration: &Calibration) -> String {
+    let (width, height) = config.screen_resolution();
+    let mut summary = vec![
+        ("Resolution".to_string(), format!("{width}x{height}")),
+        ("API".to_string(), api_value(config)),
+        ("Estimated VRAM".to_string(), format!("{:.0} MB", calibration.estimate(config.settings()))),
+    ];
+    if include_gpu {
+        let gpu = config.video_card_name().unwrap_or("unknown");
+        summary.push(("GPU".to_string(), gpu.to_string()));
+    }
+
+    let mut output = String::new();
+    match format {
+        ReportFormat::Markdown => output.push_str("## RDR2 settings\n\n"),
+        ReportFormat::Text => output.push_str("RDR2 settings\n\n"),
+    }
+    write_table(&mut output, format, None, &summary);
+    for section in XMLSection::ALL {
+        let rows: Vec<(String, String)> = config.settings().iter()
+            .filter(|setting| setting.section == section)
+            .map(|setting| (setting.nice_name.clone(), display(&setting.setting_type)))
+            .collect();
+        write_table(&mut output, format, Some(section.nice_name()), &rows);
+    }
+    // Values the game would reject, the file most likely was edited by hand
+    let issues: Vec<(String, String)> = validation::check_settings(config.settings()).into_iter()
+        .map(|issue| (issue.tag, issue.message))
+        .collect();
+    if !issues.is_empty() {
+        write_table(&mut output, format, Some("Invalid values"), &issues);
+    }
+    output
+}
+
+fn api_value(config: &SystemConfig) -> String {
+    let api = display_value(config, "API");
+    match settings::api_note(config.settings()) {
+        Some(note) => format!("{api} ({note})"),
+        None => api,
+    }
+}
+
+fn display_value(config: &SystemConfig, tag: &str) -> String {
+    match config.setting(tag) {
+        Ok(setting) => display(&setting.setting_type),
+        Err(_) => "unknown".into(),
+    }
+}
+
+fn display(setting_type: &SettingType) -> String {
+    let mut value = String::new();
+    settings::append_setting_type(&mut value, setting_type);
+    value.trim_end().to_string()
+}
+
+fn write_table(output: &mut String, format: ReportFormat, title: Option<&str>, rows: &[(String, String)]) {
+    match format {
+        ReportFormat::Markdown => {
+            if let Some(title) = title {
+                let _ = writeln!(output, "### {title}\n");
+                output.push_str("| Setting | Value |\n");
+            } else {
+                output.push_str("| | |\n");
+            }
+            output.push_str("|---|---|\n");
+            for (name, value) in rows {
+                let _ = writeln!(output, "| {} | {} |", escape_cell(name), escape_cell(value));
+            }
+        }
+        ReportFormat::Text => {
+            if let Some(title) = title {
+                let _ = writeln!(output, "[{title}]");
+            }
+            for (name, value) in rows {
+                let _ = writeln!(output, "{name:<TEXT_PADDING$}{value}");
+            }
+        }
+    }
+    output.push('\n');
+}
+
+fn escape_cell(text: &str) -> String {
+    text.replace('|', "\\|")
+}
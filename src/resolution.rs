@@ -0,0 +1,124 @@
+//! Standard display modes and keeping the width and height sliders in step.
+//!
+//! The sliders only move 8 pixels at a time, so modes and typed resolutions set both at once.
+
+use crate::error::{Error, Result};
+use crate::settings::Setting;
+use crate::settings::SettingType::Slider;
+use crate::validation;
+
+pub struct Mode {
+    pub width: usize,
+    pub height: usize,
+    pub name: &'static str,
+}
+
+/// Common modes from the smallest to the largest pixel count.
+pub const MODES: &[Mode] = &[
+    Mode { width: 1280, height: 720, name: "720p" },
+    Mode { width: 1280, height: 800, name: "Steam Deck" },
+    Mode { width: 1920, height: 1080, name: "1080p" },
+    Mode { width: 1920, height: 1200, name: "1200p" },
+    Mode { width: 2560, height: 1080, name: "Ultrawide 1080p" },
+    Mode { width: 2560, height: 1440, name: "1440p" },
+    Mode { width: 2560, height: 1600, name: "1600p" },
+    Mode { width: 3440, height: 1440, name: "Ultrawide 1440p" },
+    Mode { width: 3840, height: 1600, name: "Ultrawide 1600p" },
+    Mode { width: 5120, height: 1440, name: "Super ultrawide" },
+    Mode { width: 3840, height: 2160, name: "4K" },
+];
+
+// Ratios shown by their marketing name, ultrawide panels are all sold as 21:9
+const NAMED_RATIOS: &[(usize, usize, &str)] = &[
+    (4, 3, "4:3"),
+    (5, 4, "5:4"),
+    (16, 9, "16:9"),
+    (16, 10, "16:10"),
+    (21, 9, "21:9"),
+    (32, 9, "32:9"),
+];
+const RATIO_TOLERANCE: f64 = 0.08;
+
+pub fn mode_name(width: usize, height: usize) -> Option<&'static str> {
+    MODES.iter().find(|mode| mode.width == width && mode.height == height).map(|mode| mode.name)
+}
+
+/// Next standard mode by pixel count, wrapping around, from any resolution.
+pub fn next_mode(width: usize, height: usize, forward: bool) -> &'static Mode {
+    let pixels = width * height;
+    let found = if forward {
+        MODES.iter().find(|mode| mode.width * mode.height > pixels || (mode.width * mode.height == pixels && mode.width > width))
+    } else {
+        MODES.iter().rev().find(|mode| mode.width * mode.height < pixels || (mode.width * mode.height == pixels && mode.width < width))
+    };
+    match found {
+        Some(mode) => mode,
+        None if forward => &MODES[0],
+        None => &MODES[MODES.len() - 1],
+    }
+}
+
+/// Aspect ratio like `16:9`, common ratios are named even when the pixels are slightly off.
+pub fn aspect_ratio(width: usize, height: usize) -> String {
+    if height == 0 {
+        return "?".into();
+    }
+    let ratio = width as f64 / height as f64;
+    let distance = |(w, h, _): &&(usize, usize, &str)| (ratio - *w as f64 / *h as f64).abs();
+    let closest = NAMED_RATIOS.iter().min_by(|a, b| distance(a).total_cmp(&distance(b)));
+    if let Some(named) = closest.filter(|named| distance(named) < RATIO_TOLERANCE) {
+        return named.2.to_string();
+    }
+    let divisor = gcd(width, height);
+    format!("{}:{}", width / divisor, height / divisor)
+}
+
+fn gcd(a: usize, b: usize) -> usize {
+    if b == 0 { a.max(1) } else { gcd(b, a % b) }
+}
+
+/// Height keeping the ratio for a new width, rounded to the slider step.
+pub fn height_for_width(width: usize, ratio: (usize, usize), jump: usize) -> usize {
+    round_to(width as f64 * ratio.1 as f64 / ratio.0 as f64, jump)
+}
+
+/// Width keeping the ratio for a new height, rounded to the slider step.
+pub fn width_for_height(height: usize, ratio: (usize, usize), jump: usize) -> usize {
+    round_to(height as f64 * ratio.0 as f64 / ratio.1 as f64, jump)
+}
+
+fn round_to(value: f64, jump: usize) -> usize {
+    ((value / jump as f64).round() as usize).max(1) * jump
+}
+
+/// Reads `2560x1440` or a mode name like `1440p`, case-insensitive.
+pub fn parse(text: &str) -> Result<(usize, usize)> {
+    let text = text.trim();
+    if let Some(mode) = MODES.iter().find(|mode| mode.name.eq_ignore_ascii_case(text)) {
+        return Ok((mode.width, mode.height));
+    }
+    let invalid = || Error::Validation(format!("resolution expects WIDTHxHEIGHT or a mode like 1440p, got '{text}'"));
+    let (width, height) = text.split_once(['x', 'X']).ok_or_else(invalid)?;
+    let width = width.trim().parse().map_err(|_| invalid())?;
+    let height = height.trim().parse().map_err(|_| invalid())?;
+    Ok((width, height))
+}
+
+/// Sets both sliders, nothing is changed if either value would be rejected.
+pub fn set_resolution(settings: &mut [Setting], width: usize, height: usize) -> Result<()> {
+    let mut sliders = vec![];
+    for (index, setting) in settings.iter().enumerate() {
+        let mut candidate = setting.clone();
+        if let Slider(value, _, horizontal) = &mut candidate.setting_type {
+            *value = if *horizontal { width } else { height };
+            sliders.push((index, candidate));
+        }
+    }
+    for (_, candidate) in &sliders {
+        validation::validate(std::slice::from_ref(candidate))?;
+    }
+    for (index, candidate) in sliders {
+        settings[index] = candidate;
+    }
+    Ok(())
+}
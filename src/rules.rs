@@ -0,0 +1,150 @@
+//! Checks the catalog's [`Rule`]s against the settings and the hidden values written with them.
+
+use crate::config::SystemConfig;
+use crate::error::Result;
+use crate::settings::{self, Rule, Setting, SettingType, RULES};
+use crate::xml::{self, Element};
+
+/// Broken rule, reported on the first setting the rule names.
+#[derive(Clone, Debug)]
+pub struct Violation {
+    pub rule: Rule,
+    pub tag: &'static str,
+    /// Short form shown next to the setting, like `needs HDR`.
+    pub note: String,
+    pub message: String,
+}
+
+// Value of a tag, catalog settings first
+enum Found<'a> {
+    Setting(&'a Setting),
+    Hidden(&'a Element),
+}
+
+/// Every broken rule, `hidden` are the elements outside the catalog.
+pub fn check(settings: &[Setting], hidden: &[Element]) -> Vec<Violation> {
+    let mut violations = vec![];
+    for rule in RULES {
+        let violation = match *rule {
+            Rule::Requires(tag, required) => {
+                let (Some(dependent), Some(parent)) = (find(settings, hidden, tag), find(settings, hidden, required)) else {
+                    continue;
+                };
+                if !in_use(&dependent) || is_on(&parent) {
+                    continue;
+                }
+                Violation {
+                    rule: *rule,
+                    tag,
+                    note: format!("needs {}", name(settings, required)),
+                    message: format!("{tag} has no effect while {required} is off"),
+                }
+            }
+            Rule::Conflicts(tag, other) => {
+                let (Some(first), Some(second)) = (find(settings, hidden, tag), find(settings, hidden, other)) else {
+                    continue;
+                };
+                if !is_on(&first) || !is_on(&second) {
+                    continue;
+                }
+                Violation {
+                    rule: *rule,
+                    tag,
+                    note: format!("conflicts with {}", name(settings, other)),
+                    message: format!("{tag} and {other} are both on, that's usually a mistake"),
+                }
+            }
+            Rule::Implies(tag, implied, value) => {
+                let (Some(trigger), Some(target)) = (find(settings, hidden, tag), find(settings, hidden, implied)) else {
+                    continue;
+                };
+                let current = text(&target);
+                if !is_on(&trigger) || current == value {
+                    continue;
+                }
+                Violation {
+                    rule: *rule,
+                    tag,
+                    note: format!("{} is used at {}", name(settings, implied), value_name(settings, implied, value)),
+                    message: format!("{tag} is on so the game uses {implied}={value}, not {current}"),
+                }
+            }
+            Rule::OnlyWith(tag, other, value) => {
+                let (Some(dependent), Some(target)) = (find(settings, hidden, tag), find(settings, hidden, other)) else {
+                    continue;
+                };
+                if !in_use(&dependent) || text(&target) == value {
+                    continue;
+                }
+                Violation {
+                    rule: *rule,
+                    tag,
+                    note: format!("{} only", value_name(settings, other, value)),
+                    message: format!("{tag} only has an effect with {other}={value}"),
+                }
+            }
+        };
+        violations.push(violation);
+    }
+    violations
+}
+
+/// Sets the values implied by settings that are on, returns the `tag=value` pairs that changed.
+pub fn apply_implied(config: &mut SystemConfig) -> Result<Vec<String>> {
+    let mut changed = vec![];
+    for violation in check(config.settings(), config.defaults()) {
+        if let Rule::Implies(_, implied, value) = violation.rule {
+            config.set_value(implied, value)?;
+            changed.push(format!("{implied}={value}"));
+        }
+    }
+    Ok(changed)
+}
+
+fn find<'a>(settings: &'a [Setting], hidden: &'a [Element], tag: &str) -> Option<Found<'a>> {
+    if let Some(setting) = settings.iter().find(|setting| setting.tag == tag) {
+        return Some(Found::Setting(setting));
+    }
+    hidden.iter().find(|element| element.tag == tag).map(Found::Hidden)
+}
+
+fn is_on(found: &Found) -> bool {
+    match found {
+        Found::Setting(setting) => settings::is_on(&setting.setting_type),
+        Found::Hidden(element) => !["false", "0", "kSettingLevel_Low"].contains(&element.value.as_str()),
+    }
+}
+
+// Changed from its default, defaults are what the game writes whether they take effect or not
+fn in_use(found: &Found) -> bool {
+    match found {
+        Found::Setting(setting) => {
+            let default = settings::get_settings().into_iter().find(|default| default.tag == setting.tag);
+            default.is_some_and(|default| xml::setting_text(&default) != xml::setting_text(setting))
+        }
+        Found::Hidden(element) => {
+            let default = xml::default_elements().into_iter().find(|default| default.tag == element.tag);
+            default.is_some_and(|default| default.value != element.value)
+        }
+    }
+}
+
+fn text(found: &Found) -> String {
+    match found {
+        Found::Setting(setting) => xml::setting_text(setting),
+        Found::Hidden(element) => element.value.as_str().into(),
+    }
+}
+
+fn name(settings: &[Setting], tag: &str) -> String {
+    settings.iter().find(|setting| setting.tag == tag).map_or(tag.into(), |setting| setting.nice_name.clone())
+}
+
+// Nice name of a level option, the raw value otherwise
+fn value_name(settings: &[Setting], tag: &str, value: &str) -> String {
+    let setting_type = settings.iter().find(|setting| setting.tag == tag).map(|setting| &setting.setting_type);
+    let Some(SettingType::Level(_, selectables, _)) = setting_type else {
+        return value.into();
+    };
+    selectables.iter().find(|option| option.config_name == value).map_or(value.into(), |option| option.nice_name.clone())
+}
@@ -1,13 +1,8 @@
-use std::fs::File;
-use std::io::Write;
-use std::thread;
-use xmlwriter::{Options, XmlWriter};
-use crate::settings::SettingType::{Level, Multiplier, OnHalfOff, OnOff, Slider};
-use crate::settings::XMLSection::{AdvancedGraphics, Graphics, Video};
+//! Catalog of the settings the selector manages, in in-game order.
 
-/**
-Attribute()
- */
+use crate::error::{Error, Result};
+use crate::settings::SettingType::{Indexed, Level, Multiplier, OnHalfOff, OnOff, Range, RefreshRate, Slider};
+use crate::settings::XMLSection::{AdvancedGraphics, Graphics, Video};
 
 type VRamLevels = Vec<usize>;
 type SelectedIndex = usize;
@@ -15,22 +10,65 @@ type Value = usize;
 type MaxFactor = usize;
 type Enabled = bool;
 type Jump = usize;
+type Numerator = usize;
+type Denominator = usize;
+type Min = usize;
+type Max = usize;
+
+// The game's own spelling
+pub const VULKAN: &str = "kSettingAPI_Vulcan";
+pub const DX12: &str = "kSettingAPI_DX12";
 
+/// Refresh rates stepped through with the arrows, typed entry takes any rate.
+pub const COMMON_REFRESH_RATES: &[usize] = &[30, 50, 60, 75, 90, 100, 120, 144, 165, 170, 180, 200, 240, 280, 360, 480, 500];
+
+/// Kind of value a setting holds together with what's needed to step through it.
+#[derive(Clone)]
 pub enum SettingType {
     Level(SelectedIndex, Vec<Selectable>, VRamLevels),
     OnOff(Enabled),
     OnHalfOff(Value), // off=0, on=1, half=2
     Multiplier(Value, MaxFactor),
     Slider(Value, Jump, bool),
+    Indexed(Value, Vec<String>), // written as the number, shown by name
+    RefreshRate(Numerator, Denominator), // Hz, written to refreshRateNumerator and refreshRateDenominator
+    Range(Value, Min, Max, Jump), // any value in the range, the arrows move by the jump
 }
 
-#[derive(PartialEq)]
+/// Parent element of a setting in `system.xml`.
+#[derive(Clone, Copy, PartialEq, Debug)]
 pub enum XMLSection {
     Graphics,
     Video,
     AdvancedGraphics,
 }
 
+impl XMLSection {
+    pub const ALL: [XMLSection; 3] = [Graphics, Video, AdvancedGraphics];
+
+    pub fn tag(&self) -> &'static str {
+        match self {
+            Graphics => "graphics",
+            Video => "video",
+            AdvancedGraphics => "advancedGraphics",
+        }
+    }
+
+    pub fn nice_name(&self) -> &'static str {
+        match self {
+            Graphics => "Graphics",
+            Video => "Video",
+            AdvancedGraphics => "Advanced Graphics",
+        }
+    }
+
+    pub fn from_tag(tag: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|section| section.tag() == tag)
+    }
+}
+
+/// Setting written as `<tag>` into its section.
+#[derive(Clone)]
 pub struct Setting {
     pub tag: String,
     pub nice_name: String,
@@ -38,8 +76,6 @@ pub struct Setting {
     pub section: XMLSection,
 }
 
-pub const PIXELS_PER_1MB_VRAM: f64 = 5155.0;
-
 impl Setting {
     pub fn off_medium_high(section: XMLSection, tag: &str, nice_name: &str, step1: usize, step2: usize) -> Self {
         let off = Selectable::new("Low (OFF)".into(), "kSettingLevel_Low".into()); // ??
@@ -111,9 +147,9 @@ impl Setting {
         }
     }
     pub fn api_options(section: XMLSection, tag: &str, nice_name: &str) -> Self {
-        let vulcan = Selectable::new("Vulcan".into(), "kSettingAPI_Vulcan".into());
-        let drx12 = Selectable::new("DirectX12".into(), "kSettingAPI_DX12".into());
-        let options = vec![vulcan, drx12];
+        let vulkan = Selectable::new("Vulkan".into(), VULKAN.into());
+        let drx12 = Selectable::new("DirectX12".into(), DX12.into());
+        let options = vec![vulkan, drx12];
         let vram = vec![0, 0];
         Self {
             nice_name: nice_name.into(),
@@ -133,6 +169,43 @@ impl Setting {
             section,
         }
     }
+    pub fn indexed(section: XMLSection, tag: &str, nice_name: &str, names: &[&str], selected: usize) -> Self {
+        Self {
+            nice_name: nice_name.into(),
+            tag: tag.into(),
+            setting_type: Indexed(selected, names.iter().map(|name| name.to_string()).collect()),
+            section,
+        }
+    }
+    pub fn refresh_rate(section: XMLSection, tag: &str, nice_name: &str, hertz: usize) -> Self {
+        Self {
+            nice_name: nice_name.into(),
+            tag: tag.into(),
+            setting_type: RefreshRate(hertz, 1),
+            section,
+        }
+    }
+    pub fn range(section: XMLSection, tag: &str, nice_name: &str, curr_val: usize, min: usize, max: usize, jump: usize) -> Self {
+        Self {
+            nice_name: nice_name.into(),
+            tag: tag.into(),
+            setting_type: Range(curr_val, min, max, jump),
+            section,
+        }
+    }
+    /// Named options without a VRAM cost of their own, `selected` is the default.
+    pub fn named_options(section: XMLSection, tag: &str, nice_name: &str, options: &[(&str, &str)], selected: usize) -> Self {
+        let selectables = options.iter()
+            .map(|(nice_name, config_name)| Selectable::new(nice_name.to_string(), config_name.to_string()))
+            .collect::<Vec<_>>();
+        let vram = vec![0; selectables.len() - 1];
+        Self {
+            nice_name: nice_name.into(),
+            tag: tag.into(),
+            setting_type: Level(selected, selectables, vram),
+            section,
+        }
+    }
     pub fn low_medium_high(section: XMLSection, tag: &str, nice_name: &str, step1: usize, step2: usize) -> Self {
         let low = Selectable::new("Low".into(), "kSettingLevel_Low".into());
         let medium = Selectable::new("Medium".into(), "kSettingLevel_Medium".into());
@@ -148,6 +221,8 @@ impl Setting {
     }
 }
 
+/// Option of a `Level` setting, `config_name` is what ends up in the file.
+#[derive(Clone)]
 pub struct Selectable {
     pub nice_name: String,
     pub config_name: String,
@@ -159,11 +234,132 @@ impl Selectable {
     }
 }
 
+impl SettingType {
+    /// Steps one value down, returns false if it's already at the bottom.
+    pub fn decrease(&mut self) -> bool {
+        match self {
+            Level(selected_index, _, _) => {
+                if *selected_index == 0 {
+                    return false;
+                }
+                *selected_index -= 1;
+            }
+            OnOff(enabled) => {
+                *enabled = !*enabled;
+            }
+            Multiplier(value, _) => {
+                if *value == 0 {
+                    return false;
+                }
+                if *value <= 2 {
+                    *value = 0;
+                } else {
+                    *value /= 2;
+                }
+            }
+            OnHalfOff(value) => {
+                // OFF ON HALF
+                *value = match *value {
+                    0 => 2,
+                    1 => 0,
+                    _ => 1,
+                };
+            }
+            Slider(value, jump, _) => {
+                if *value < *jump * 2 {
+                    return false;
+                }
+                *value -= *jump;
+            }
+            Indexed(value, _) => {
+                if *value == 0 {
+                    return false;
+                }
+                *value -= 1;
+            }
+            Range(value, min, _, jump) => {
+                if *value <= *min {
+                    return false;
+                }
+                *value = value.saturating_sub(*jump).max(*min);
+            }
+            RefreshRate(numerator, denominator) => {
+                let hertz = *numerator as f64 / (*denominator).max(1) as f64;
+                let Some(lower) = COMMON_REFRESH_RATES.iter().rev().find(|rate| (**rate as f64) < hertz) else {
+                    return false;
+                };
+                (*numerator, *denominator) = (*lower, 1);
+            }
+        }
+        true
+    }
+
+    /// Steps one value up, returns false if it's already at the top.
+    pub fn increase(&mut self) -> bool {
+        match self {
+            Level(selected_index, selectable, _) => {
+                if *selected_index + 1 >= selectable.len() {
+                    return false;
+                }
+                *selected_index += 1;
+            }
+            OnOff(enabled) => {
+                *enabled = !*enabled;
+            }
+            Multiplier(value, max_factor) => {
+                if *value == 0 {
+                    *value = 2;
+                } else if value < max_factor && *value >= 2 {
+                    *value *= 2;
+                } else {
+                    return false;
+                }
+            }
+            OnHalfOff(value) => {
+                // OFF ON HALF
+                *value = match *value {
+                    0 => 1,
+                    1 => 2,
+                    _ => 0,
+                };
+            }
+            Slider(value, jump, _) => {
+                *value += *jump;
+            }
+            Indexed(value, names) => {
+                if *value + 1 >= names.len() {
+                    return false;
+                }
+                *value += 1;
+            }
+            Range(value, _, max, jump) => {
+                if *value >= *max {
+                    return false;
+                }
+                *value = (*value + *jump).min(*max);
+            }
+            RefreshRate(numerator, denominator) => {
+                let hertz = *numerator as f64 / (*denominator).max(1) as f64;
+                let Some(higher) = COMMON_REFRESH_RATES.iter().find(|rate| **rate as f64 > hertz) else {
+                    return false;
+                };
+                (*numerator, *denominator) = (*higher, 1);
+            }
+        }
+        true
+    }
+}
+
+/// Every setting at its default value with the built-in level costs, see [`Calibration::apply`](crate::calibration::Calibration::apply).
 pub fn get_settings() -> Vec<Setting> {
     // Settings maintaining in-game order
     let settings = vec![
+        Setting::indexed(Video, "windowed", "Screen Type", &["Fullscreen", "Windowed", "Windowed Borderless"], 2),
+        Setting::indexed(Video, "adapterIndex", "Output Adapter", &["First GPU", "Second GPU", "Third GPU", "Fourth GPU"], 0),
+        Setting::indexed(Video, "outputIndex", "Output Monitor", &["First Monitor", "Second Monitor", "Third Monitor", "Fourth Monitor"], 0),
         Setting::screen_pixels(Video, "screenWidthWindowed", "Width Pixels", 1024, 8, true),
         Setting::screen_pixels(Video, "screenHeightWindowed", "Height Pixels", 768, 8, false),
+        Setting::refresh_rate(Video, "refreshRateNumerator", "Refresh Rate", 60),
         Setting::on_off(Video, "tripleBuffered", "Triple Buffering", true),
         Setting::on_half_off(Video, "vSync", "VSync"),
 
@@ -183,10 +379,33 @@ pub fn get_settings() -> Vec<Setting> {
         Setting::off_medium_high(Graphics, "taa", "TAA Quality", 6, 0), // Off is actually Low
         Setting::on_off(Graphics, "fxaaEnabled", "FXAA", false),
         Setting::multiplier(Graphics, "msaa", "MSAA", 8),
+        Setting::indexed(Graphics, "dlssIndex", "NVIDIA DLSS", &["Off", "On"], 0),
+        // NGX quality modes in the order of their values
+        Setting::indexed(Graphics, "dlssQuality", "DLSS Quality", &["Performance", "Balanced", "Quality", "Ultra Performance", "Ultra Quality", "DLAA"], 5),
+        Setting::on_off(Graphics, "fsrEnabled", "AMD FSR", false),
+        Setting::indexed(Graphics, "fsrQuality", "FSR Quality", &["Ultra Quality", "Quality", "Balanced", "Performance"], 1),
         Setting::on_off(Graphics, "hdr", "HDR", true),
         Setting::on_off(Graphics, "hdrFilmicMode", "HDR Filmic Mode", true),
+        Setting::range(Graphics, "hdrIntensity", "HDR Intensity", 100, 0, 200, 5),
+        Setting::range(Graphics, "hdrPeakBrightness", "HDR Peak Brightness (nits)", 1000, 100, 10000, 50),
+        Setting::range(Graphics, "gamma", "HDR Gamma", 15, 0, 30, 1),
 
         Setting::api_options(AdvancedGraphics, "API", "Graphical API"),
+        Setting::on_off(AdvancedGraphics, "asyncComputeEnabled", "Async Compute", false),
+        Setting::on_off(AdvancedGraphics, "transferQueuesEnabled", "Transfer Queues", true),
+        Setting::named_options(AdvancedGraphics, "scalingMode", "Resolution Scale", &[
+            ("0.333", "kSettingScale_Mode1o3"),
+            ("0.500", "kSettingScale_Mode1o2"),
+            ("0.667", "kSettingScale_Mode2o3"),
+            ("0.750", "kSettingScale_Mode3o4"),
+            ("0.833", "kSettingScale_Mode5o6"),
+            ("Off", "kSettingScale_Mode1o1"),
+            ("1.250", "kSettingScale_Mode5o4"),
+            ("1.500", "kSettingScale_Mode3o2"),
+            ("1.750", "kSettingScale_Mode7o4"),
+            ("2.000", "kSettingScale_Mode2o1"),
+            ("2.500", "kSettingScale_Mode5o2"),
+        ], 5),
         Setting::low_medium_high_ultra(AdvancedGraphics, "treeQuality", "Tree Quality", 0, 0, 0),
         Setting::low_medium_high_ultra(AdvancedGraphics, "decalQuality", "Decal Quality", 0, 0, 0),
         Setting::off_medium_high(AdvancedGraphics, "furDisplayQuality", "Fur Quality", 0, 0),
@@ -205,207 +424,190 @@ pub fn get_settings() -> Vec<Setting> {
     settings
 }
 
-pub fn get_setting_index_by_tag(settings: &[Setting], tag: &str) -> Option<usize> {
-    for (i, setting) in settings.iter().enumerate() {
-        if setting.tag == tag {
-            return Some(i);
-        }
-    }
-    return None;
+/// Relation between two values, settings and hidden elements alike, checked by [`crate::rules`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Rule {
+    /// The first only has an effect while the second is on.
+    Requires(&'static str, &'static str),
+    /// Both on at once is usually a mistake.
+    Conflicts(&'static str, &'static str),
+    /// While the first is on the game uses the second at the given value.
+    Implies(&'static str, &'static str, &'static str),
+    /// The first only has an effect while the second is at the given value.
+    OnlyWith(&'static str, &'static str, &'static str),
 }
 
-pub fn commit_xml_write(settings: Vec<Setting>) {
-    let thread_handle = thread::spawn(move || {
-        retrieve_video_card_name()
+pub const RULES: &[Rule] = &[
+    Rule::Requires("hdrFilmicMode", "hdr"),
+    Rule::Requires("hdrIntensity", "hdr"),
+    Rule::Requires("hdrPeakBrightness", "hdr"),
+    Rule::Requires("gamma", "hdr"),
+    Rule::Requires("treeTessellationEnabled", "tessellation"),
+    Rule::Requires("dlssQuality", "dlssIndex"),
+    Rule::Requires("fsrQuality", "fsrEnabled"),
+    Rule::Requires("ssaoType", "ssao"),
+    Rule::Requires("ssaoFullScreenEnabled", "ssao"),
+    Rule::Requires("ssdoSampleCount", "ssao"),
+    Rule::Requires("ssdoUseDualRadii", "ssao"),
+    Rule::Requires("ssdoResolution", "ssao"),
+    Rule::Requires("ssdoTAABlendEnabled", "ssao"),
+    // MSAA on top of a post-process AA blurs what it paid for
+    Rule::Conflicts("msaa", "taa"),
+    Rule::Conflicts("msaa", "fxaaEnabled"),
+    Rule::Conflicts("dlssIndex", "fsrEnabled"),
+    // The game greys out the resolution scale while DLSS is on
+    Rule::Implies("dlssIndex", "scalingMode", "kSettingScale_Mode1o1"),
+    // Dedicated transfer queues are a Vulkan feature, DX12 copies on its own queues regardless
+    Rule::OnlyWith("transferQueuesEnabled", "API", VULKAN),
+];
+
+// Companion toggles recommended for each API, applied when switching to it
+const API_DEFAULTS: &[(&str, &[(&str, bool)])] = &[
+    (VULKAN, &[("asyncComputeEnabled", true), ("transferQueuesEnabled", true)]),
+    (DX12, &[("asyncComputeEnabled", false)]),
+];
+
+/// Sets the companion toggles recommended for the selected API, returns the indices that changed.
+pub fn apply_api_defaults(settings: &mut [Setting]) -> Vec<usize> {
+    let api = settings.iter().find(|setting| setting.tag == "API").and_then(|setting| match &setting.setting_type {
+        Level(selected_index, selectables, _) => selectables.get(*selected_index).map(|option| option.config_name.clone()),
+        _ => None,
     });
-    let opt = Options {
-        use_single_quote: false, // RDR2 has double quote
-        ..Options::default()
+    let Some((_, defaults)) = API_DEFAULTS.iter().find(|(config_name, _)| Some(*config_name) == api.as_deref()) else {
+        return vec![];
     };
-    let mut xml = XmlWriter::new(opt);
-    xml.write_declaration();
-
-    xml.start_element("rage__fwuiSystemSettingsCollection");
-
-    xml.start_element("version");
-    xml.write_attribute("value", "37");
-    xml.end_element();
-
-    xml.start_element("configSource");
-    xml.set_preserve_whitespaces(true);
-    xml.write_text("kSettingsConfig_Auto");
-    xml.end_element();
-    xml.set_preserve_whitespaces(false);
-
-    xml.start_element("graphics");
-    write_default_graphics(&mut xml);
-    write_options_section(Graphics, &settings, &mut xml);
-    xml.end_element();
-
-    xml.start_element("video");
-    write_default_video(&mut xml);
-    write_options_section(Video, &settings, &mut xml);
-    xml.end_element();
-
-    xml.start_element("advancedGraphics");
-    write_default_advanced_graphics(&mut xml);
-    write_options_section(AdvancedGraphics, &settings, &mut xml);
-    xml.end_element();
-
-    xml.start_element("videoCardDescription");
-    xml.set_preserve_whitespaces(true);
-    // Read existing video card name (config will be reset if video card desc doesn't match, just why)
-    if let Some(card_name) = thread_handle.join().expect("Thread panicked?") {
-        xml.write_text(&card_name);
-    } else {
-        eprintln!("Video card name wasn't fetched, change it manually");
-        xml.write_text("VIDEO_CARD_NAME");
-    }
-
-    xml.end_element();
-    xml.set_preserve_whitespaces(false);
-
-    let content = xml.end_document();
-    let mut file = File::create("system.xml").expect("Couldn't create file");
-    file.write_all(content.as_bytes()).expect("Failed to write to file");
-}
-
-fn write_options_section(section: XMLSection, settings: &Vec<Setting>, xml: &mut XmlWriter) {
-    for setting in settings.iter() {
-        if setting.section != section {
+    let mut changed = vec![];
+    for (tag, recommended) in defaults.iter() {
+        let Ok(index) = get_setting_index_by_tag(settings, tag) else {
             continue;
-        }
-        xml.start_element(&setting.tag);
-        match &setting.setting_type {
-            Level(index, selectables, _) => {
-                xml.set_preserve_whitespaces(true);
-                xml.write_text(&selectables[*index].config_name)
-            },
-            OnOff(on) => {
-                let boolean = if *on { "true" } else { "false" };
-                xml.write_attribute("value", boolean);
+        };
+        if let OnOff(enabled) = &mut settings[index].setting_type {
+            if enabled != recommended {
+                *enabled = *recommended;
+                changed.push(index);
             }
-            Multiplier(value, _) => xml.write_attribute("value", &value),
-            OnHalfOff(vsync) => xml.write_attribute("value", vsync),
-            Slider(pixels, _, _) => xml.write_attribute("value", pixels)
         }
-        xml.end_element();
-        xml.set_preserve_whitespaces(false);
     }
+    changed
 }
 
-type VideoCard = String;
-fn retrieve_video_card_name() -> Option<VideoCard> {
-    let Some(mut dir) = std::env::home_dir() else {
-        return None
+/// Note for the selected API on this platform, DX12 on Linux goes through vkd3d-proton.
+pub fn api_note(settings: &[Setting]) -> Option<&'static str> {
+    let api = settings.iter().find(|setting| setting.tag == "API")?;
+    let Level(selected_index, selectables, _) = &api.setting_type else {
+        return None;
     };
-    dir = dir.join("Documents")
-        .join("Rockstar Games")
-        .join("Red Dead Redemption 2")
-        .join("Settings")
-        .join("system.xml");
-    if !dir.exists() {
-        return None
-    }
-    println!("Reading video card name from system.xml at:");
-    println!("{dir:?}");
-    let Ok(content) = std::fs::read_to_string(dir) else {
-        return None
-    };
-    let Some(desc_index) = content.rfind("<videoCardDescription>") else {
-        return None
-    };
-    let name_start = desc_index + 22;
-    let bytes = content.as_bytes();
-    for i in name_start..bytes.len() {
-        if bytes[i] != b'<' {
-            continue
-        }
-        let owned_bytes = bytes[name_start..i].to_owned();
-        let card_name = String::from_utf8(owned_bytes).expect("NOT UTF8?");
-        println!("{card_name}");
-        return Some(card_name);
+    if cfg!(target_os = "linux") && selectables.get(*selected_index).is_some_and(|option| option.config_name == DX12) {
+        return Some("runs through vkd3d-proton");
     }
     None
 }
 
-fn write_default_graphics(xml: &mut XmlWriter) {
-    write_element("dlssIndex", "0", xml);
-    write_element("dlssQuality", "5", xml);
-    write_element("graphicsQualityPreset", "0.5", xml);
-    write_element("hdrIntensity", "100", xml);
-    write_element("hdrPeakBrightness", "1000", xml);
-    write_element("gamma", "15", xml);
-    write_element("hdrSettingsMigrated", "true", xml);
+/// Tag of the setting this one only has an effect with.
+pub fn depends_on(tag: &str) -> Option<&'static str> {
+    RULES.iter().find_map(|rule| match rule {
+        Rule::Requires(dependent, required) if *dependent == tag => Some(*required),
+        _ => None,
+    })
 }
 
-fn write_default_video(xml: &mut XmlWriter) {
-    write_element("adapterIndex", "0", xml); // output adapter
-    write_element("outputIndex", "0", xml); // output monitor
+/// False while the setting it depends on is off.
+pub fn is_active(settings: &[Setting], setting: &Setting) -> bool {
+    let Some(parent) = depends_on(&setting.tag) else {
+        return true;
+    };
+    settings.iter().find(|setting| setting.tag == parent).is_none_or(|parent| is_on(&parent.setting_type))
+}
 
-    write_element("resolutionIndexWindowed", "0", xml);
-    write_element("resolutionIndex", "1", xml);
+/// Whether a setting counts as switched on, levels are off at `Low` which the game shows as `OFF`.
+pub fn is_on(setting_type: &SettingType) -> bool {
+    match setting_type {
+        Level(selected_index, selectables, _) => selectables.get(*selected_index).is_some_and(|option| option.config_name != "kSettingLevel_Low"),
+        OnOff(enabled) => *enabled,
+        OnHalfOff(value) | Multiplier(value, _) | Indexed(value, _) => *value > 0,
+        Slider(..) | RefreshRate(..) | Range(..) => true,
+    }
+}
 
-    write_element("screenWidth", "1240", xml);
-    write_element("screenHeight", "720", xml);
+pub fn get_setting_index_by_tag(settings: &[Setting], tag: &str) -> Result<usize> {
+    settings.iter()
+        .position(|setting| setting.tag == tag)
+        .ok_or_else(|| Error::MissingSetting(tag.into()))
+}
 
-    write_element("refreshRateIndex", "0", xml);
-    write_element("refreshRateNumerator", "60", xml);
-    write_element("refreshRateDenominator", "1", xml);
+/// Appends the value the way it's shown in-game, followed by a newline.
+pub fn append_setting_type(format: &mut String, setting_type: &SettingType) {
+    match setting_type {
+        Level(selected_index, selectable, _) => match selectable.get(*selected_index) {
+            Some(option) => format.push_str(&option.nice_name),
+            None => format.push_str(&format!("{selected_index} (invalid)")),
+        },
+        OnOff(enabled) => {
+            if *enabled {
+                format.push_str("ON");
+            } else {
+                format.push_str("OFF");
+            }
+        }
+        Multiplier(value, _) => {
+            if *value == 0 {
+                format.push_str("OFF");
+            } else {
+                format.push('X');
+                format.push_str(&value.to_string());
+            }
+        }
+        OnHalfOff(value) => {
+            if *value == 0 {
+                format.push_str("OFF");
+            } else if *value == 1 {
+                format.push_str("ON");
+            } else if *value == 2 {
+                format.push_str("HALF");
+            } else {
+                format.push_str(&format!("{value} (invalid)"));
+            }
+        }
+        Slider(value, _, _) => {
+            format.push_str(&value.to_string());
+        }
+        Indexed(value, names) => match names.get(*value) {
+            Some(name) => format.push_str(name),
+            None => format.push_str(&format!("{value} (invalid)")),
+        },
+        RefreshRate(numerator, denominator) => {
+            format.push_str(&hertz_text(*numerator, *denominator));
+        }
+        Range(value, _, _, _) => {
+            format.push_str(&value.to_string());
+        }
+    }
 
-    write_element("windowed", "2", xml);
-    write_element("pauseOnFocusLoss", "false", xml); // make selectable
-    write_element("constrainMousePointer", "false", xml); // make selectable
+    format.push('\n');
 }
 
-fn write_default_advanced_graphics(xml: &mut XmlWriter) {
-    write_element("locked", "false", xml);
-    write_element("asyncComputeEnabled", "false", xml);
-    write_element("transferQueuesEnabled", "true", xml);
-    write_element("motionBlurLimit", "16.0", xml);
-    write_element("waterSimulationQuality", "3", xml); // make selectable
-    write_text_element("waterLightingQuality", "kSettingLevel_Ultra", xml); //make selectable
-    write_element("maxTexUpgradesPerFrame", "5", xml);
-    // check
-    write_text_element("shadowGrassShadows", "kSettingLevel_High", xml);
-    write_element("shadowParticleShadows", "true", xml);
-    write_element("shadowLongShadows", "true", xml);
-    write_element("directionalShadowsAlpha", "false", xml);
-    write_element("worldHeightShadowQuality", "1.0", xml);
-    write_element("directionalScreenSpaceShadowQuality", "1.0", xml);
-    write_element("ambientMaskVolumesHighPrecision", "true", xml);
-    write_text_element("scatteringVolumeQuality", "kSettingLevel_High", xml);
-    write_text_element("volumetricsRaymarchQuality", "kSettingLevel_High", xml);
-    write_text_element("volumetricsLightingQuality", "kSettingLevel_High", xml);
-    write_element("volumetricsRaymarchResolutionUnclamped", "true", xml);
-    write_text_element("terrainShadowQuality", "kSettingLevel_Ultra", xml);
-    write_element("ssaoFullScreenEnabled", "false", xml);
-    write_element("ssaoType", "0", xml);
-    write_element("ssdoSampleCount", "4", xml);
-    write_element("ssdoUseDualRadii", "false", xml);
-    write_text_element("ssdoResolution", "kSettingLevel_Low", xml);
-    write_element("ssdoTAABlendEnabled", "true", xml);
-    write_element("ssroSampleCount", "2", xml);
-    write_element("probeRelightEveryFrame", "false", xml);
-    write_text_element("scalingMode", "kSettingScale_Mode1o1", xml);
-    write_element("reflectionMSAA", "0", xml);
-    write_element("lodScale", "1.0", xml);
-    write_element("grassLod", "3.0", xml);
-    write_element("pedLodBias", "0", xml);
-    write_element("vehicleLodBias", "0", xml);
-    write_element("sharpenIntensity", "1", xml);
+/// `144 Hz` for whole rates, `59.94 Hz` otherwise.
+pub fn hertz_text(numerator: usize, denominator: usize) -> String {
+    if denominator == 0 {
+        return format!("{numerator}/0 Hz");
+    }
+    if numerator.is_multiple_of(denominator) {
+        return format!("{} Hz", numerator / denominator);
+    }
+    format!("{:.2} Hz", numerator as f64 / denominator as f64)
 }
 
-fn write_element(name: &str, val: &str, xml: &mut XmlWriter) {
-    xml.start_element(name);
-    xml.write_attribute("value", val);
-    xml.end_element();
+/// Reads `144`, `59.94` or `60000/1001` as a numerator and denominator.
+pub fn parse_hertz(text: &str) -> Option<(usize, usize)> {
+    let text = text.trim().trim_end_matches("Hz").trim();
+    if let Some((numerator, denominator)) = text.split_once('/') {
+        return Some((numerator.trim().parse().ok()?, denominator.trim().parse().ok()?));
+    }
+    let (whole, fraction) = text.split_once('.').unwrap_or((text, ""));
+    if whole.is_empty() || !fraction.chars().all(|chr| chr.is_ascii_digit()) || fraction.len() > 3 {
+        return None;
+    }
+    let denominator = 10usize.pow(fraction.len() as u32);
+    let numerator = format!("{whole}{fraction}").parse().ok()?;
+    Some((numerator, denominator))
 }
-
-fn write_text_element(name: &str, text: &str, xml: &mut XmlWriter) {
-    xml.start_element(name);
-    xml.set_preserve_whitespaces(true);
-    xml.write_text(text);
-    xml.end_element();
-    xml.set_preserve_whitespaces(false);
-}
\ No newline at end of file
@@ -0,0 +1,226 @@
+//! Short codes for sharing settings between players.
+//!
+//! Every catalog setting is packed into as few bits as its kind needs, in catalog order,
+//! followed by a CRC-16 and written in Crockford base32 grouped by dashes.
+//! The video card name is left out on purpose so a code works on any machine.
+//!
+//! The layout follows the catalog, so adding or reordering settings needs a new `CODE_VERSION`.
+
+use crate::error::{Error, Result};
+use crate::settings::Setting;
+use crate::settings::SettingType::{Indexed, Level, Multiplier, OnHalfOff, OnOff, Range, RefreshRate, Slider};
+use crate::validation;
+use crate::xml;
+
+pub const CODE_VERSION: u8 = 5;
+
+const ALPHABET: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+const GROUP_LENGTH: usize = 5;
+const SLIDER_BITS: u32 = 13;
+// Validation keeps rates at 500 Hz with a denominator of at most 1001
+const NUMERATOR_BITS: u32 = 19;
+const DENOMINATOR_BITS: u32 = 10;
+
+/// Packs the settings into a code like `0G4C8-2Q0RT-...`, invalid values that wouldn't survive the trip are rejected.
+pub fn encode(settings: &[Setting]) -> Result<String> {
+    validation::validate(settings)?;
+    let mut bits = BitWriter::default();
+    bits.push(CODE_VERSION as u32, 8);
+    for setting in settings {
+        let (value, width) = setting_bits(setting);
+        if value >> width != 0 {
+            return Err(Error::Validation(format!("{}: {} doesn't fit in a settings code", setting.tag, xml::setting_text(setting))));
+        }
+        bits.push(value as u32, width);
+    }
+    let mut bytes = bits.into_bytes();
+    let checksum = crc16(&bytes);
+    bytes.extend_from_slice(&checksum.to_be_bytes());
+    Ok(group(&to_base32(&bytes)))
+}
+
+/// Applies a code to the settings, nothing is changed if the code is invalid.
+pub fn decode(code: &str, settings: &mut [Setting]) -> Result<()> {
+    let bytes = from_base32(code)?;
+    if bytes.len() < 3 {
+        return Err(invalid("too short"));
+    }
+    let (payload, checksum) = bytes.split_at(bytes.len() - 2);
+    let checksum = u16::from_be_bytes([checksum[0], checksum[1]]);
+    if crc16(payload) != checksum {
+        return Err(invalid("checksum doesn't match, was it copied completely?"));
+    }
+    let mut bits = BitReader { bytes: payload, position: 0 };
+    let version = bits.read(8)? as u8;
+    if version != CODE_VERSION {
+        return Err(invalid(&format!("made by a version using layout {version}, this one reads {CODE_VERSION}")));
+    }
+    let mut decoded = settings.to_vec();
+    for setting in decoded.iter_mut() {
+        let (_, width) = setting_bits(setting);
+        let value = bits.read(width)?;
+        apply_bits(setting, value)?;
+    }
+    validation::validate(&decoded)?;
+    settings.clone_from_slice(&decoded);
+    Ok(())
+}
+
+fn invalid(message: &str) -> Error {
+    Error::Validation(format!("invalid settings code: {message}"))
+}
+
+// Value to pack and how many bits the setting takes
+fn setting_bits(setting: &Setting) -> (usize, u32) {
+    match &setting.setting_type {
+        Level(selected_index, selectables, _) => (*selected_index, bits_for(selectables.len())),
+        OnOff(enabled) => (*enabled as usize, 1),
+        OnHalfOff(value) => (*value, 2),
+        // 0 is off, otherwise the exponent of the factor
+        Multiplier(value, max_factor) => {
+            let exponent = if *value == 0 { 0 } else { value.trailing_zeros() as usize };
+            (exponent, bits_for(max_factor.trailing_zeros() as usize + 1))
+        }
+        Slider(value, jump, _) => (*value / *jump, SLIDER_BITS),
+        Indexed(value, names) => (*value, bits_for(names.len())),
+        Range(value, min, max, _) => (value.saturating_sub(*min), bits_for(*max - *min + 1)),
+        RefreshRate(numerator, denominator) => {
+            (*numerator << DENOMINATOR_BITS | *denominator, NUMERATOR_BITS + DENOMINATOR_BITS)
+        }
+    }
+}
+
+fn apply_bits(setting: &mut Setting, bits: u32) -> Result<()> {
+    let tag = setting.tag.clone();
+    match &mut setting.setting_type {
+        Level(selected_index, selectables, _) => {
+            if bits as usize >= selectables.len() {
+                return Err(invalid(&format!("{tag} has no level {bits}")));
+            }
+            *selected_index = bits as usize;
+        }
+        OnOff(enabled) => *enabled = bits == 1,
+        OnHalfOff(value) => *value = bits as usize,
+        Multiplier(value, _) => *value = if bits == 0 { 0 } else { 1 << bits },
+        Slider(value, jump, _) => *value = bits as usize * *jump,
+        Indexed(value, _) => *value = bits as usize,
+        Range(value, min, _, _) => *value = *min + bits as usize,
+        RefreshRate(numerator, denominator) => {
+            *numerator = (bits >> DENOMINATOR_BITS) as usize;
+            *denominator = (bits & ((1 << DENOMINATOR_BITS) - 1)) as usize;
+        }
+    }
+    Ok(())
+}
+
+fn bits_for(options: usize) -> u32 {
+    match options {
+        0 | 1 => 1,
+        _ => usize::BITS - (options - 1).leading_zeros(),
+    }
+}
+
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    bit_length: usize,
+}
+
+impl BitWriter {
+    fn push(&mut self, value: u32, width: u32) {
+        for shift in (0..width).rev() {
+            if self.bit_length.is_multiple_of(8) {
+                self.bytes.push(0);
+            }
+            let bit = ((value >> shift) & 1) as u8;
+            let last = self.bytes.len() - 1;
+            self.bytes[last] |= bit << (7 - self.bit_length % 8);
+            self.bit_length += 1;
+        }
+    }
+
+    fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+}
+
+struct BitReader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl BitReader<'_> {
+    fn read(&mut self, width: u32) -> Result<u32> {
+        let mut value = 0;
+        for _ in 0..width {
+            let Some(byte) = self.bytes.get(self.position / 8) else {
+                return Err(invalid("too short for this catalog"));
+            };
+            let bit = (byte >> (7 - self.position % 8)) & 1;
+            value = (value << 1) | bit as u32;
+            self.position += 1;
+        }
+        Ok(value)
+    }
+}
+
+// CRC-16/CCITT-FALSE
+fn crc16(bytes: &[u8]) -> u16 {
+    let mut crc: u16 = 0xFFFF;
+    for byte in bytes {
+        crc ^= (*byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 { (crc << 1) ^ 0x1021 } else { crc << 1 };
+        }
+    }
+    crc
+}
+
+fn to_base32(bytes: &[u8]) -> String {
+    let mut text = String::new();
+    let mut buffer: u32 = 0;
+    let mut buffered = 0;
+    for byte in bytes {
+        buffer = (buffer << 8) | *byte as u32;
+        buffered += 8;
+        while buffered >= 5 {
+            buffered -= 5;
+            text.push(ALPHABET[((buffer >> buffered) & 31) as usize] as char);
+        }
+    }
+    if buffered > 0 {
+        text.push(ALPHABET[((buffer << (5 - buffered)) & 31) as usize] as char);
+    }
+    text
+}
+
+// Case-insensitive, dashes and spaces are ignored, O/I/L read as 0/1/1
+fn from_base32(code: &str) -> Result<Vec<u8>> {
+    let mut bytes = vec![];
+    let mut buffer: u32 = 0;
+    let mut buffered = 0;
+    for chr in code.chars() {
+        let chr = match chr.to_ascii_uppercase() {
+            '-' | ' ' => continue,
+            'O' => '0',
+            'I' | 'L' => '1',
+            chr => chr,
+        };
+        let Some(value) = ALPHABET.iter().position(|letter| *letter as char == chr) else {
+            return Err(invalid(&format!("'{chr}' is not part of a code")));
+        };
+        buffer = (buffer << 5) | value as u32;
+        buffered += 5;
+        if buffered >= 8 {
+            buffered -= 8;
+            bytes.push((buffer >> buffered) as u8);
+        }
+    }
+    Ok(bytes)
+}
+
+fn group(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let groups: Vec<String> = chars.chunks(GROUP_LENGTH).map(|chunk| chunk.iter().collect()).collect();
+    groups.join("-")
+}
@@ -0,0 +1,144 @@
+//! Benchmark sweeps: one variant of a base config per value of the settings to vary.
+//!
+//! Each variant is installed into the settings directory, then the sweep waits for the next
+//! benchmark report before moving on. The game is run by hand or by another tool,
+//! only the directory is watched.
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use crate::bench::{self, Benchmark};
+use crate::config::SystemConfig;
+use crate::error::{Error, Result};
+use crate::{rules, settings, xml};
+
+pub const INSTALLED_FILE: &str = "system.xml";
+pub const CSV_FILE: &str = "sweep.csv";
+
+/// A setting and the values it's swept over, parsed from `tag=a,b,c`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Vary {
+    pub tag: String,
+    pub values: Vec<String>,
+}
+
+impl Vary {
+    pub fn parse(text: &str) -> Result<Self> {
+        let Some((tag, values)) = text.split_once('=') else {
+            return Err(Error::Validation(format!("expected tag=value,value..., got '{text}'")));
+        };
+        let values: Vec<String> = values.split(',').map(|value| value.trim().to_string()).filter(|value| !value.is_empty()).collect();
+        if values.is_empty() {
+            return Err(Error::Validation(format!("{tag} has no values to sweep")));
+        }
+        Ok(Self { tag: tag.trim().into(), values })
+    }
+}
+
+/// The base with one value changed, `tag` is `None` for the base itself.
+#[derive(Clone)]
+pub struct Variant {
+    pub tag: Option<String>,
+    pub value: String,
+    pub config: SystemConfig,
+}
+
+impl Variant {
+    pub fn label(&self) -> String {
+        match &self.tag {
+            Some(tag) => format!("{tag}={}", self.value),
+            None => "base".into(),
+        }
+    }
+}
+
+/// A variant and the benchmark it was run with.
+pub struct Run {
+    pub variant: Variant,
+    pub benchmark: Benchmark,
+}
+
+/// The base followed by one variant per value, the same way `set` changes them:
+/// the API brings its recommended companions and implied values follow.
+pub fn variants(base: &SystemConfig, varies: &[Vary]) -> Result<Vec<Variant>> {
+    let mut variants = vec![Variant { tag: None, value: String::new(), config: base.clone() }];
+    for vary in varies {
+        for value in &vary.values {
+            let mut config = base.clone();
+            config.set_value(&vary.tag, value)?;
+            if vary.tag == "API" {
+                settings::apply_api_defaults(config.settings_mut());
+            }
+            rules::apply_implied(&mut config)?;
+            config.check_settings()?;
+            variants.push(Variant { tag: Some(vary.tag.clone()), value: value.clone(), config });
+        }
+    }
+    Ok(variants)
+}
+
+/// Backs up the installed `system.xml` and writes the variant in its place.
+pub fn install(variant: &Variant, settings_dir: &Path) -> Result<Option<PathBuf>> {
+    let installed = settings_dir.join(INSTALLED_FILE);
+    let backup = xml::backup_file(&installed)?;
+    variant.config.write_file(&installed)?;
+    Ok(backup)
+}
+
+/// Reports already in the directory, so only new ones count as a run.
+pub fn existing_reports(benchmarks_dir: &Path) -> HashSet<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(benchmarks_dir) else {
+        return HashSet::new();
+    };
+    entries.flatten().map(|entry| entry.path()).collect()
+}
+
+/// Polls until a report not in `seen` reads fine, the game may still be writing one that doesn't.
+pub fn wait_for_report(benchmarks_dir: &Path, seen: &HashSet<PathBuf>, interval: Duration, timeout: Option<Duration>) -> Result<Benchmark> {
+    let start = Instant::now();
+    loop {
+        for path in existing_reports(benchmarks_dir) {
+            if seen.contains(&path) || path.extension().is_none_or(|ext| ext != "txt") {
+                continue;
+            }
+            if let Ok(benchmark) = Benchmark::read_file(&path) {
+                return Ok(benchmark);
+            }
+        }
+        if timeout.is_some_and(|timeout| start.elapsed() >= timeout) {
+            return Err(Error::Validation(format!("no new benchmark report in {}", benchmarks_dir.display())));
+        }
+        std::thread::sleep(interval);
+    }
+}
+
+/// One line per run with its average and worst FPS and average frame time.
+pub fn to_csv(runs: &[Run]) -> String {
+    let mut csv = String::from("setting,value,report,time,avg_fps,min_fps,avg_frame_ms\n");
+    for run in runs {
+        let benchmark = &run.benchmark;
+        let report = benchmark.path.file_name().unwrap_or_default().to_string_lossy();
+        let frame_times: Vec<f64> = benchmark.passes.iter().filter_map(|pass| pass.frame_time.map(|stats| stats.avg)).collect();
+        let frame_time = if frame_times.is_empty() {
+            String::new()
+        } else {
+            format!("{:.3}", frame_times.iter().sum::<f64>() / frame_times.len() as f64)
+        };
+        csv += &format!("{},{},{},{},{:.2},{:.2},{frame_time}\n",
+            csv_field(run.variant.tag.as_deref().unwrap_or("base")),
+            csv_field(&run.variant.value),
+            csv_field(&report),
+            bench::format_time(benchmark.modified),
+            benchmark.avg_fps(),
+            benchmark.min_fps());
+    }
+    csv
+}
+
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.into()
+    }
+}
@@ -0,0 +1,375 @@
+//! Picks the highest quality levels that fit in a VRAM budget.
+//!
+//! This is a multiple-choice knapsack over the quality settings: every level of a setting
+//! costs its `VRamLevels` steps and is worth its position times the setting's weight.
+//! Costs are rounded up to whole MB and scores are integers, so the answer is exact
+//! and the same inputs always give the same settings. The same table gives the whole
+//! tradeoff curve, see [`frontier`].
+//!
+//! Settings the catalog says conflict (MSAA and TAA) are one choice over the combinations
+//! that don't have both on, so a tuned config never breaks a `Conflicts` rule.
+
+use crate::calibration::Calibration;
+use crate::error::{Error, Result};
+use crate::settings::{self, Rule, Setting, SettingType, RULES};
+use crate::settings::SettingType::{Level, Multiplier};
+use crate::vram;
+
+const QUALITY_LEVEL_PREFIX: &str = "kSettingLevel_";
+const DEFAULT_WEIGHT: u32 = 1;
+// Divisible by every (options - 1) up to 4, keeps scores integer
+const SCORE_SCALE: u32 = 12;
+
+/// How much each setting is worth per level, settings not listed use a weight of 1.
+pub fn default_weights() -> Vec<(&'static str, u32)> {
+    vec![
+        ("textureQuality", 10),
+        ("anisotropicFiltering", 8),
+        ("lightingQuality", 6),
+        ("shadowQuality", 6),
+        ("ambientLightingQuality", 5),
+        ("ssao", 4),
+        ("volumetricsQuality", 4),
+        ("farShadowQuality", 3),
+        ("reflectionQuality", 3),
+        ("taa", 3),
+        ("waterQuality", 2),
+        ("mirrorQuality", 2),
+        ("particleQuality", 2),
+        ("tessellation", 2),
+        ("treeQuality", 2),
+    ]
+}
+
+#[derive(Clone)]
+pub struct TuneOptions {
+    pub vram_budget: usize,
+    pub weights: Vec<(String, u32)>,
+    /// Tags of settings that keep their current value.
+    pub locked: Vec<String>,
+    /// Numbers the estimates use, the built-in ones unless set.
+    pub calibration: Calibration,
+}
+
+impl TuneOptions {
+    pub fn new(vram_budget: usize) -> Self {
+        let weights = default_weights().into_iter().map(|(tag, weight)| (tag.to_string(), weight)).collect();
+        Self { vram_budget, weights, locked: vec![], calibration: Calibration::builtin() }
+    }
+
+    /// Estimates with fitted numbers, the settings tuned get its level costs.
+    pub fn calibration(mut self, calibration: Calibration) -> Self {
+        self.calibration = calibration;
+        self
+    }
+
+    /// Overrides the weight of a single setting.
+    pub fn weight(mut self, tag: &str, weight: u32) -> Self {
+        self.weights.retain(|(existing, _)| existing != tag);
+        self.weights.push((tag.to_string(), weight));
+        self
+    }
+
+    pub fn lock(mut self, tag: &str) -> Self {
+        self.locked.push(tag.to_string());
+        self
+    }
+
+    fn weight_of(&self, tag: &str) -> u32 {
+        self.weights.iter().find(|(existing, _)| existing == tag).map_or(DEFAULT_WEIGHT, |(_, weight)| *weight)
+    }
+}
+
+/// Every value a tunable setting can take from lowest to highest, `None` for settings the tuner leaves alone.
+pub fn tunable_values(setting: &Setting) -> Option<Vec<SettingType>> {
+    let mut lowest = setting.setting_type.clone();
+    match &mut lowest {
+        Level(selected_index, selectables, _) => {
+            let is_quality = selectables.iter().all(|option| option.config_name.starts_with(QUALITY_LEVEL_PREFIX));
+            if !is_quality {
+                return None;
+            }
+            *selected_index = 0;
+        }
+        Multiplier(value, _) => *value = 0,
+        _ => return None,
+    }
+    let mut values = vec![lowest.clone()];
+    while lowest.increase() {
+        values.push(lowest.clone());
+    }
+    Some(values)
+}
+
+// One option per combination of values for the settings at `indices`
+struct Choice {
+    indices: Vec<usize>,
+    values: Vec<Vec<SettingType>>,
+    costs: Vec<usize>,
+    scores: Vec<u32>,
+}
+
+/// Raises the settings as far as the budget allows, returns the new VRAM estimate.
+///
+/// Settings the tuner doesn't touch (toggles, the API, the resolution) count towards the budget as they are.
+pub fn tune(settings: &mut [Setting], options: &TuneOptions) -> Result<f64> {
+    options.calibration.apply(settings);
+    let (choices, capacity) = prepare(settings, options)?;
+    let table = Table::solve(&choices, capacity);
+    apply(settings, &choices, &table.picks_at(&choices, capacity));
+    Ok(options.calibration.estimate(settings))
+}
+
+/// Configuration on the cost/quality tradeoff curve.
+pub struct FrontierPoint {
+    pub vram: f64,
+    pub score: u32,
+    pub settings: Vec<Setting>,
+}
+
+/// Every configuration no other beats on both VRAM and quality, from cheapest to richest.
+///
+/// Only budgets up to `vram_budget` are explored, locked settings stay as they are in every point.
+pub fn frontier(settings: &[Setting], options: &TuneOptions) -> Result<Vec<FrontierPoint>> {
+    let mut settings = settings.to_vec();
+    options.calibration.apply(&mut settings);
+    let (choices, capacity) = prepare(&settings, options)?;
+    let richest = choices.iter().map(|choice| choice.costs.iter().max().copied().unwrap_or(0)).sum();
+    let capacity = capacity.min(richest);
+    let table = Table::solve(&choices, capacity);
+
+    let mut points = vec![];
+    for budget in 0..=capacity {
+        // A budget only adds a point if it buys a better score than one MB less
+        if budget > 0 && table.best[budget] == table.best[budget - 1] {
+            continue;
+        }
+        let mut point = settings.to_vec();
+        apply(&mut point, &choices, &table.picks_at(&choices, budget));
+        points.push(FrontierPoint { vram: options.calibration.estimate(&point), score: table.best[budget], settings: point });
+    }
+    Ok(points)
+}
+
+/// Setting lowered by [`lower_to_fit`] with the value it had before.
+pub struct Lowered {
+    pub index: usize,
+    pub before: SettingType,
+}
+
+/// Lowers the lowest-weight unlocked settings one level at a time until the estimate fits the budget.
+///
+/// The setting at `keep` is never touched, it's usually the one that was just raised.
+/// Unlocked settings conflicting with it are switched off first.
+/// Nothing is changed if the budget can't be reached.
+pub fn lower_to_fit(settings: &mut [Setting], keep: usize, options: &TuneOptions) -> Result<Vec<Lowered>> {
+    let mut lowered: Vec<Lowered> = vec![];
+    let mut candidate = settings.to_vec();
+    options.calibration.apply(&mut candidate);
+    let megapixels = vram::render_megapixels(settings);
+    if settings::is_on(&candidate[keep].setting_type) {
+        for index in 0..candidate.len() {
+            let setting = &candidate[index];
+            if !is_tuned(setting, options) || !settings::is_on(&setting.setting_type) || !conflict(&candidate[keep].tag, &setting.tag) {
+                continue;
+            }
+            let Some(lowest) = tunable_values(setting).and_then(|values| values.into_iter().next()) else {
+                continue;
+            };
+            lowered.push(Lowered { index, before: setting.setting_type.clone() });
+            candidate[index].setting_type = lowest;
+        }
+    }
+    while options.calibration.estimate(&candidate) > options.vram_budget as f64 {
+        // Lowest weight first, then the step saving the most
+        let mut best: Option<(usize, u32, f64, SettingType)> = None;
+        for (index, setting) in candidate.iter().enumerate() {
+            if index == keep || !is_tuned(setting, options) {
+                continue;
+            }
+            let mut lower = setting.clone();
+            if !lower.setting_type.decrease() {
+                continue;
+            }
+            let saving = vram::setting_vram(setting, megapixels) - vram::setting_vram(&lower, megapixels);
+            if saving <= 0.0 {
+                continue;
+            }
+            let weight = options.weight_of(&setting.tag);
+            let better = best.as_ref().is_none_or(|(_, best_weight, best_saving, _)| {
+                weight < *best_weight || (weight == *best_weight && saving > *best_saving)
+            });
+            if better {
+                best = Some((index, weight, saving, lower.setting_type));
+            }
+        }
+        let Some((index, _, _, value)) = best else {
+            let message = format!("can't fit {} MB by lowering other settings", options.vram_budget);
+            return Err(Error::Validation(message));
+        };
+        if !lowered.iter().any(|entry| entry.index == index) {
+            lowered.push(Lowered { index, before: candidate[index].setting_type.clone() });
+        }
+        candidate[index].setting_type = value;
+    }
+    settings.clone_from_slice(&candidate);
+    Ok(lowered)
+}
+
+fn is_tuned(setting: &Setting, options: &TuneOptions) -> bool {
+    !options.locked.contains(&setting.tag) && tunable_values(setting).is_some()
+}
+
+fn conflict(tag: &str, other: &str) -> bool {
+    RULES.iter().any(|rule| matches!(rule, Rule::Conflicts(first, second) if (*first == tag && *second == other) || (*first == other && *second == tag)))
+}
+
+// Value of a single setting with its cost and score
+struct Candidate {
+    value: SettingType,
+    cost: usize,
+    score: u32,
+}
+
+// Tunable settings and the MB left for them once everything else is counted
+fn prepare(settings: &[Setting], options: &TuneOptions) -> Result<(Vec<Choice>, usize)> {
+    // The tuner never changes the resolution, so neither does the cost of a value
+    let megapixels = vram::render_megapixels(settings);
+    let mut tuned: Vec<(usize, Vec<Candidate>)> = vec![];
+    for (index, setting) in settings.iter().enumerate() {
+        if options.locked.contains(&setting.tag) {
+            continue;
+        }
+        let Some(values) = tunable_values(setting) else {
+            continue;
+        };
+        let weight = options.weight_of(&setting.tag);
+        let steps = (values.len() - 1).max(1) as u32;
+        // Settings the tuner leaves as they are keep their value, so conflicting with one that's on rules out being on
+        let blocked = settings.iter()
+            .any(|other| !is_tuned(other, options) && settings::is_on(&other.setting_type) && conflict(&setting.tag, &other.tag));
+        let candidates = values.into_iter().enumerate()
+            .filter(|(i, value)| *i == 0 || !blocked || !settings::is_on(value))
+            .map(|(i, value)| Candidate { cost: value_cost(setting, &value, megapixels), score: weight * i as u32 * SCORE_SCALE / steps, value })
+            .collect();
+        tuned.push((index, candidates));
+    }
+
+    // Conflicting settings end up in the same group
+    let mut groups: Vec<Vec<usize>> = vec![];
+    for (position, (index, _)) in tuned.iter().enumerate() {
+        let (mut joined, rest): (Vec<Vec<usize>>, Vec<Vec<usize>>) = groups.into_iter()
+            .partition(|group| group.iter().any(|member| conflict(&settings[tuned[*member].0].tag, &settings[*index].tag)));
+        let mut group: Vec<usize> = joined.drain(..).flatten().collect();
+        group.push(position);
+        group.sort();
+        groups = rest;
+        groups.push(group);
+    }
+    groups.sort();
+    let choices: Vec<Choice> = groups.iter().map(|group| combine(settings, &tuned, group)).collect();
+
+    // Everything the tuner can't change, with tunable settings at their lowest
+    let mut baseline = settings.to_vec();
+    apply(&mut baseline, &choices, &vec![0; choices.len()]);
+    let fixed = options.calibration.estimate(&baseline);
+    if fixed > options.vram_budget as f64 {
+        let message = format!("the lowest settings already need {fixed:.0} MB, over the budget of {} MB", options.vram_budget);
+        return Err(Error::Validation(message));
+    }
+    let capacity = (options.vram_budget as f64 - fixed).floor() as usize;
+    Ok((choices, capacity))
+}
+
+// Every combination of the group's values without two conflicting settings on, cheapest first
+fn combine(settings: &[Setting], tuned: &[(usize, Vec<Candidate>)], group: &[usize]) -> Choice {
+    let indices: Vec<usize> = group.iter().map(|member| tuned[*member].0).collect();
+    let mut combinations: Vec<Vec<&Candidate>> = vec![vec![]];
+    for member in group {
+        combinations = combinations.into_iter()
+            .flat_map(|combination| tuned[*member].1.iter().map(move |candidate| {
+                let mut next = combination.clone();
+                next.push(candidate);
+                next
+            }))
+            .collect();
+    }
+    combinations.retain(|combination| {
+        let on: Vec<&str> = combination.iter().zip(&indices)
+            .filter(|(candidate, _)| settings::is_on(&candidate.value))
+            .map(|(_, index)| settings[*index].tag.as_str())
+            .collect();
+        on.iter().enumerate().all(|(i, tag)| on[i + 1..].iter().all(|other| !conflict(tag, other)))
+    });
+    // Stable, so the lowest values stay the first option
+    combinations.sort_by_key(|combination| combination.iter().map(|candidate| candidate.cost).sum::<usize>());
+    Choice {
+        indices,
+        values: combinations.iter().map(|combination| combination.iter().map(|candidate| candidate.value.clone()).collect()).collect(),
+        costs: combinations.iter().map(|combination| combination.iter().map(|candidate| candidate.cost).sum()).collect(),
+        scores: combinations.iter().map(|combination| combination.iter().map(|candidate| candidate.score).sum()).collect(),
+    }
+}
+
+fn apply(settings: &mut [Setting], choices: &[Choice], picks: &[usize]) {
+    for (choice, &pick) in choices.iter().zip(picks) {
+        for (index, value) in choice.indices.iter().zip(&choice.values[pick]) {
+            settings[*index].setting_type = value.clone();
+        }
+    }
+}
+
+// Cost in whole MB of the value on top of the setting's lowest value
+fn value_cost(setting: &Setting, value: &SettingType, render_megapixels: f64) -> usize {
+    let mut candidate = setting.clone();
+    candidate.setting_type = value.clone();
+    vram::setting_vram(&candidate, render_megapixels).ceil() as usize
+}
+
+// best[b] is the highest score reachable with at most b MB
+struct Table {
+    best: Vec<u32>,
+    // Option picked for each choice at each budget, given the choices before it
+    picks: Vec<Vec<u8>>,
+}
+
+impl Table {
+    // Ties keep the cheaper earlier option
+    fn solve(choices: &[Choice], capacity: usize) -> Self {
+        let mut best = vec![0u32; capacity + 1];
+        let mut picks: Vec<Vec<u8>> = Vec::with_capacity(choices.len());
+        for choice in choices {
+            let mut next = vec![0u32; capacity + 1];
+            let mut picked = vec![0u8; capacity + 1];
+            for budget in 0..=capacity {
+                let mut best_score = None;
+                for (option, (&cost, &score)) in choice.costs.iter().zip(&choice.scores).enumerate() {
+                    if cost > budget {
+                        continue;
+                    }
+                    let total = best[budget - cost] + score;
+                    if best_score.is_none_or(|current| total > current) {
+                        best_score = Some(total);
+                        picked[budget] = option as u8;
+                    }
+                }
+                next[budget] = best_score.unwrap_or(0);
+            }
+            best = next;
+            picks.push(picked);
+        }
+        Self { best, picks }
+    }
+
+    // Walks back from the budget to the option of every choice
+    fn picks_at(&self, choices: &[Choice], budget: usize) -> Vec<usize> {
+        let mut remaining = budget;
+        let mut result = vec![0; choices.len()];
+        for (i, choice) in choices.iter().enumerate().rev() {
+            let option = self.picks[i][remaining] as usize;
+            result[i] = option;
+            remaining -= choice.costs[option];
+        }
+        result
+    }
+}
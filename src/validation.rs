@@ -0,0 +1,130 @@
+//! Checks that settings hold values the game accepts.
+
+use crate::error::{Error, Result};
+use crate::settings::{self, Setting};
+use crate::settings::SettingType::{Indexed, Level, Multiplier, OnHalfOff, OnOff, Range, RefreshRate, Slider};
+
+const MIN_HERTZ: f64 = 24.0;
+const MAX_HERTZ: f64 = 500.0;
+// Monitors report rates like 60000/1001, larger denominators don't fit the share codes
+const MAX_DENOMINATOR: usize = 1001;
+
+/// Problem found in a single setting.
+#[derive(Clone, Debug)]
+pub struct Issue {
+    pub tag: String,
+    pub message: String,
+}
+
+/// Every problem found in the settings, empty when the game would accept them.
+pub fn check_settings(settings: &[Setting]) -> Vec<Issue> {
+    let mut issues = vec![];
+    for setting in settings {
+        if let Some(message) = check_setting(setting) {
+            issues.push(Issue { tag: setting.tag.clone(), message });
+        }
+    }
+    issues
+}
+
+/// Fails with the first problem found.
+pub fn validate(settings: &[Setting]) -> Result<()> {
+    match check_settings(settings).into_iter().next() {
+        Some(issue) => Err(Error::Validation(format!("{}: {}", issue.tag, issue.message))),
+        None => Ok(()),
+    }
+}
+
+/// Sets the value of a numeric setting, nothing is changed if it would be rejected.
+pub fn set_number(setting: &mut Setting, new_value: usize) -> Result<()> {
+    let mut candidate = setting.clone();
+    match &mut candidate.setting_type {
+        Multiplier(value, _) | Slider(value, _, _) | OnHalfOff(value) | Indexed(value, _) | Range(value, _, _, _) => *value = new_value,
+        RefreshRate(numerator, denominator) => (*numerator, *denominator) = (new_value, 1),
+        _ => return Err(Error::Validation(format!("{} is not a numeric setting", setting.tag))),
+    }
+    if let Some(message) = check_setting(&candidate) {
+        return Err(Error::Validation(format!("{}: {message}", setting.tag)));
+    }
+    *setting = candidate;
+    Ok(())
+}
+
+/// Sets a numeric setting from typed text, refresh rates also take `59.94` or `60000/1001`.
+pub fn set_typed(setting: &mut Setting, text: &str) -> Result<()> {
+    let invalid = || Error::Validation(format!("{} expects a number, got '{text}'", setting.tag));
+    if let RefreshRate(..) = setting.setting_type {
+        let (numerator, denominator) = settings::parse_hertz(text).ok_or_else(invalid)?;
+        let mut candidate = setting.clone();
+        candidate.setting_type = RefreshRate(numerator, denominator);
+        if let Some(message) = check_setting(&candidate) {
+            return Err(Error::Validation(format!("{}: {message}", setting.tag)));
+        }
+        *setting = candidate;
+        return Ok(());
+    }
+    let value = text.trim().parse().map_err(|_| invalid())?;
+    set_number(setting, value)
+}
+
+/// Problem with a value read from a file, `None` when the selector can hold it.
+/// Rates aren't checked, the game writes whatever the monitor reports.
+pub(crate) fn check_read(setting: &Setting) -> Option<String> {
+    match setting.setting_type {
+        RefreshRate(..) => None,
+        _ => check_setting(setting),
+    }
+}
+
+fn check_setting(setting: &Setting) -> Option<String> {
+    match &setting.setting_type {
+        Level(selected_index, selectables, _) => {
+            if *selected_index >= selectables.len() {
+                return Some(format!("level index {selected_index} out of {} levels", selectables.len()));
+            }
+        }
+        OnOff(_) => {}
+        OnHalfOff(value) => {
+            if *value > 2 {
+                return Some(format!("{value} is not one of 0 (off), 1 (on), 2 (half)"));
+            }
+        }
+        Multiplier(value, max_factor) => {
+            if *value != 0 && !value.is_power_of_two() {
+                return Some(format!("{value} is not 0 or a power of two"));
+            }
+            if value > max_factor {
+                return Some(format!("{value} is above the maximum of {max_factor}"));
+            }
+        }
+        Slider(value, _, _) => {
+            if *value == 0 {
+                return Some("a size of 0 pixels".into());
+            }
+        }
+        Indexed(value, names) => {
+            if *value >= names.len() {
+                let choices: Vec<String> = names.iter().enumerate().map(|(i, name)| format!("{i} ({name})")).collect();
+                return Some(format!("{value} is not one of {}", choices.join(", ")));
+            }
+        }
+        Range(value, min, max, _) => {
+            if value < min || value > max {
+                return Some(format!("{value} is outside {min} to {max}"));
+            }
+        }
+        RefreshRate(numerator, denominator) => {
+            if *denominator == 0 {
+                return Some(format!("{numerator}/0 has a zero denominator"));
+            }
+            if *denominator > MAX_DENOMINATOR {
+                return Some(format!("denominator {denominator} is above the maximum of {MAX_DENOMINATOR}"));
+            }
+            let hertz = *numerator as f64 / *denominator as f64;
+            if !(MIN_HERTZ..=MAX_HERTZ).contains(&hertz) {
+                return Some(format!("{} is outside {MIN_HERTZ} to {MAX_HERTZ} Hz", settings::hertz_text(*numerator, *denominator)));
+            }
+        }
+    }
+    None
+}
@@ -0,0 +1,146 @@
+//! The video card description the game checks before accepting a config.
+
+use std::path::{Path, PathBuf};
+use crate::error::{Error, Result};
+use crate::xml::Document;
+
+pub type VideoCard = String;
+
+pub const PROFILES_DIR: &str = "profiles";
+
+/// The game resets the whole config if `videoCardDescription` doesn't match the installed card.
+#[derive(Default)]
+pub struct VideoCardDescription {
+    pub name: Option<VideoCard>,
+    // Name found in system.xml of the machine we're writing for
+    pub current: Option<VideoCard>,
+    // Names seen in the current file, backups and profiles
+    pub known: Vec<VideoCard>,
+}
+
+pub enum CardCheck {
+    Matching,
+    Missing,
+    NoCurrentFile,
+    Mismatch(VideoCard),
+}
+
+impl VideoCardDescription {
+    /// Name in the installed `system.xml` and the ones seen next to it and in `profiles`.
+    pub fn load() -> Result<Self> {
+        Self::load_from(settings_dir().as_deref(), Path::new(PROFILES_DIR))
+    }
+
+    /// Like [`load`](Self::load) with the settings and profiles directories given.
+    pub fn load_from(settings_dir: Option<&Path>, profiles_dir: &Path) -> Result<Self> {
+        let current = match settings_dir {
+            Some(dir) => read_video_card_name(&dir.join("system.xml"))?,
+            None => None,
+        };
+        let mut known = vec![];
+        if let Some(card_name) = &current {
+            known.push(card_name.clone());
+        }
+        for card_name in scan_video_card_names(settings_dir, profiles_dir) {
+            if !known.contains(&card_name) {
+                known.push(card_name);
+            }
+        }
+        Ok(Self {
+            name: current.clone(),
+            current,
+            known,
+        })
+    }
+
+    /// Cycles through the names seen in backups and profiles.
+    pub fn cycle(&mut self, forward: bool) {
+        if self.known.is_empty() {
+            return;
+        }
+        let len = self.known.len();
+        let position = self.name.as_ref().and_then(|name| self.known.iter().position(|known| known == name));
+        let next = match (position, forward) {
+            (None, true) => 0,
+            (None, false) => len - 1,
+            (Some(i), true) => (i + 1) % len,
+            (Some(i), false) => (i + len - 1) % len,
+        };
+        self.name = Some(self.known[next].clone());
+    }
+
+    pub fn set_typed(&mut self, typed: &str) {
+        let trimmed = typed.trim();
+        if trimmed.is_empty() {
+            self.name = None;
+        } else {
+            self.name = Some(trimmed.to_string());
+        }
+    }
+
+    pub fn require_name(&self) -> Result<&str> {
+        match &self.name {
+            Some(name) => Ok(name),
+            None => Err(Error::Validation("video card name is missing, pick or type one first".into())),
+        }
+    }
+
+    pub fn check(&self) -> CardCheck {
+        let Some(name) = &self.name else {
+            return CardCheck::Missing;
+        };
+        match &self.current {
+            None => CardCheck::NoCurrentFile,
+            Some(current) if current == name => CardCheck::Matching,
+            Some(current) => CardCheck::Mismatch(current.clone()),
+        }
+    }
+}
+
+/// `Documents/Rockstar Games/Red Dead Redemption 2/Settings` in the home directory.
+pub fn settings_dir() -> Option<PathBuf> {
+    let home = std::env::home_dir()?;
+    let dir = home.join("Documents")
+        .join("Rockstar Games")
+        .join("Red Dead Redemption 2")
+        .join("Settings");
+    Some(dir)
+}
+
+/// Card name stored in a `system.xml`, a missing file is not an error, the name just isn't known yet.
+pub fn read_video_card_name(path: &Path) -> Result<Option<VideoCard>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let document = Document::read_file(path)?;
+    Ok(document.video_card().map(String::from))
+}
+
+// Backups sit next to system.xml in the Settings directory, profiles are kept locally
+fn scan_video_card_names(settings_dir: Option<&Path>, profiles_dir: &Path) -> Vec<VideoCard> {
+    let mut dirs = vec![profiles_dir];
+    if let Some(dir) = settings_dir {
+        dirs.push(dir);
+    }
+    let mut names = vec![];
+    for dir in dirs {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            continue
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let is_config = path.extension().is_some_and(|ext| ext == "xml" || ext == "bak");
+            if !is_config {
+                continue
+            }
+            // A broken backup shouldn't hide the names found in the others
+            if let Ok(Some(card_name)) = read_video_card_name(&path) {
+                if !names.contains(&card_name) {
+                    names.push(card_name);
+                }
+            }
+        }
+    }
+    names.sort();
+    names
+}
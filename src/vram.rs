@@ -0,0 +1,157 @@
+//! VRAM usage estimation.
+//!
+//! Level costs come from `VRamLevels` in the catalog, the resolution adds a cost per pixel above
+//! the 1024x768 baseline that `MIN_VRAM` was measured at. The constants are the built-in numbers,
+//! a fitted [`Calibration`] replaces them where it's passed in.
+//!
+//! Settings that allocate render targets also cost MB per megapixel of the effective render
+//! resolution, the screen resolution scaled by DLSS, FSR or `scalingMode`. Their `VRamLevels` are
+//! the fixed part measured at the baseline, the scaled part adds for every megapixel above it.
+
+use crate::calibration::Calibration;
+use crate::settings::Setting;
+use crate::settings::SettingType::{Indexed, Level, Multiplier, OnOff, Slider};
+
+/// Highest low [test on minimal settings (1024 x 768)] claimed=1537
+pub const MIN_VRAM: usize = 1670;
+pub const RECOMMENDED_VRAM: usize = 6144;
+pub const PIXELS_PER_1MB_VRAM: f64 = 5155.0;
+pub const BASE_WIDTH: usize = 1024;
+pub const BASE_HEIGHT: usize = 768;
+
+const SCALING_MODE_TAG: &str = "scalingMode";
+const SCALING_MODE_PREFIX: &str = "kSettingScale_Mode";
+
+// Render scale of each axis by `dlssQuality`, in the order of the NGX values
+const DLSS_SCALES: &[f64] = &[0.5, 0.58, 0.667, 0.333, 0.77, 1.0];
+// Render scale of each axis by `fsrQuality`, FSR 1.0 divides by 1.3, 1.5, 1.7 and 2
+const FSR_SCALES: &[f64] = &[1.0 / 1.3, 1.0 / 1.5, 1.0 / 1.7, 0.5];
+
+// MB per megapixel for each step, in the order of `VRamLevels` or of the multiplier's factors.
+// Not measured, these are sized from the render targets each step is assumed to add, 1 byte per
+// pixel being 1 MB per megapixel: MSAA ~24 bytes (G-buffer, HDR color and depth) for every extra
+// sample, so 2x adds one sample, 4x two more and 8x four more. SSAO 16-bit occlusion buffers for
+// the first two steps and a 32-bit one for the last, volumetrics and reflections doubling per step,
+// TAA an RGBA16F history (8 bytes) and a 4-byte velocity buffer. `calibrate fit` keeps them as
+// they are, readings at several resolutions with the same settings would show how far off they are.
+const SCALED_COSTS: &[(&str, &[f64])] = &[
+    ("msaa", &[24.0, 48.0, 96.0]),
+    ("ssao", &[2.0, 2.0, 4.0]),
+    ("volumetricsQuality", &[3.0, 6.0, 12.0]),
+    ("reflectionQuality", &[4.0, 8.0, 16.0]),
+    ("taa", &[8.0, 4.0]),
+];
+
+/// Estimated VRAM usage in MB of the whole configuration with the built-in numbers.
+pub fn estimate_vram(settings: &[Setting]) -> f64 {
+    estimate_with(settings, &Calibration::builtin())
+}
+
+/// Estimate using the base and per-pixel numbers of `calibration`, level costs are read from the settings.
+pub fn estimate_with(settings: &[Setting], calibration: &Calibration) -> f64 {
+    let megapixels = render_megapixels(settings);
+    let levels: f64 = settings.iter().map(|setting| setting_vram(setting, megapixels)).sum();
+    calibration.min_vram + levels + pixels_above_base(settings) / calibration.pixels_per_mb
+}
+
+/// Cost in MB of the selected value on top of the lowest one, at the given render megapixels.
+pub fn setting_vram(setting: &Setting, render_megapixels: f64) -> f64 {
+    (fixed_vram(setting) + scaled_vram(setting, render_megapixels)).max(0.0)
+}
+
+/// Part of the cost that doesn't depend on the resolution.
+pub fn fixed_vram(setting: &Setting) -> f64 {
+    match &setting.setting_type {
+        Level(selected_index, _, vram_levels) => vram_levels.iter().take(*selected_index).sum::<usize>() as f64,
+        _ => 0.0,
+    }
+}
+
+/// Part of the cost that grows with the render resolution, 0 at the baseline.
+pub fn scaled_vram(setting: &Setting, render_megapixels: f64) -> f64 {
+    let Some((_, per_megapixel)) = SCALED_COSTS.iter().find(|(tag, _)| *tag == setting.tag) else {
+        return 0.0;
+    };
+    let steps = match &setting.setting_type {
+        Level(selected_index, _, _) => *selected_index,
+        Multiplier(value, _) if *value > 0 => value.trailing_zeros() as usize,
+        _ => 0,
+    };
+    let base_megapixels = (BASE_WIDTH * BASE_HEIGHT) as f64 / 1e6;
+    per_megapixel.iter().take(steps).sum::<f64>() * (render_megapixels - base_megapixels)
+}
+
+/// Cost in MB of the resolution relative to the 1024x768 baseline, negative below it.
+pub fn resolution_vram(settings: &[Setting], calibration: &Calibration) -> f64 {
+    pixels_above_base(settings) / calibration.pixels_per_mb
+}
+
+fn pixels_above_base(settings: &[Setting]) -> f64 {
+    let (width, height) = resolution(settings);
+    (width * height) as f64 - (BASE_WIDTH * BASE_HEIGHT) as f64
+}
+
+/// Width and height taken from the horizontal and vertical sliders, baseline if missing.
+pub fn resolution(settings: &[Setting]) -> (usize, usize) {
+    let mut width = BASE_WIDTH;
+    let mut height = BASE_HEIGHT;
+    for setting in settings {
+        if let Slider(value, _, horizontal) = setting.setting_type {
+            if horizontal {
+                width = value;
+            } else {
+                height = value;
+            }
+        }
+    }
+    (width, height)
+}
+
+/// Scale of each axis the game renders at, an enabled upscaler replaces `scalingMode`.
+pub fn render_scale(settings: &[Setting]) -> f64 {
+    if let Some(scale) = upscaler_scale(settings, "dlssIndex", "dlssQuality", DLSS_SCALES) {
+        return scale;
+    }
+    if let Some(scale) = upscaler_scale(settings, "fsrEnabled", "fsrQuality", FSR_SCALES) {
+        return scale;
+    }
+    let scaling_mode = settings.iter().find(|setting| setting.tag == SCALING_MODE_TAG);
+    let Some(Level(selected_index, selectables, _)) = scaling_mode.map(|setting| &setting.setting_type) else {
+        return 1.0;
+    };
+    // kSettingScale_Mode2o3 renders at 2/3 of the width and height
+    let ratio = selectables.get(*selected_index)
+        .and_then(|option| option.config_name.strip_prefix(SCALING_MODE_PREFIX))
+        .and_then(|ratio| ratio.split_once('o'))
+        .and_then(|(numerator, denominator)| Some((numerator.parse::<f64>().ok()?, denominator.parse::<f64>().ok()?)));
+    match ratio {
+        Some((numerator, denominator)) if denominator > 0.0 => numerator / denominator,
+        _ => 1.0,
+    }
+}
+
+// Scale of the quality mode when the upscaler is enabled
+fn upscaler_scale(settings: &[Setting], enabled_tag: &str, quality_tag: &str, scales: &[f64]) -> Option<f64> {
+    let find = |tag: &str| settings.iter().find(|setting| setting.tag == tag).map(|setting| &setting.setting_type);
+    let enabled = match find(enabled_tag)? {
+        OnOff(enabled) => *enabled,
+        Indexed(value, _) => *value > 0,
+        _ => false,
+    };
+    match find(quality_tag)? {
+        Indexed(quality, _) if enabled => scales.get(*quality).copied(),
+        _ => None,
+    }
+}
+
+/// Width and height the game renders at before scaling to the screen.
+pub fn render_resolution(settings: &[Setting]) -> (usize, usize) {
+    let (width, height) = resolution(settings);
+    let scale = render_scale(settings);
+    ((width as f64 * scale).round() as usize, (height as f64 * scale).round() as usize)
+}
+
+pub fn render_megapixels(settings: &[Setting]) -> f64 {
+    let (width, height) = render_resolution(settings);
+    (width * height) as f64 / 1e6
+}
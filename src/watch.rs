@@ -0,0 +1,289 @@
+//! Notices when the game rewrites or resets `system.xml` and compares it with the profile we applied.
+//!
+//! On Linux the settings directory is watched with inotify, elsewhere or when inotify isn't
+//! available the file's modification time and size are polled.
+
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+use crate::config::SystemConfig;
+use crate::error::{Error, Result};
+use crate::xml;
+
+// Time for the game to finish writing after the first event
+const SETTLE: Duration = Duration::from_millis(200);
+
+/// Value that differs between the profile and the file, `tag` is `videoCardDescription` for the card name.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Difference {
+    pub tag: String,
+    pub expected: String,
+    pub found: String,
+}
+
+pub enum Change {
+    Removed,
+    Unreadable(Error),
+    /// Rewritten with the same values, the game does that on exit.
+    Unchanged,
+    Changed {
+        differences: Vec<Difference>,
+        /// The video card name changed or everything we set went back to its default.
+        reset: bool,
+        found: SystemConfig,
+    },
+}
+
+/// Compares the file with the profile.
+pub fn check(profile: &SystemConfig, path: &Path) -> Change {
+    if !path.exists() {
+        return Change::Removed;
+    }
+    let found = match SystemConfig::read_file(path) {
+        Ok(found) => found,
+        Err(err) => return Change::Unreadable(err),
+    };
+    let differences = differences(profile, &found);
+    if differences.is_empty() {
+        return Change::Unchanged;
+    }
+    let reset = is_reset(profile, &found);
+    Change::Changed { differences, reset, found }
+}
+
+/// Card name, catalog settings and hidden values that differ, in file order.
+pub fn differences(expected: &SystemConfig, found: &SystemConfig) -> Vec<Difference> {
+    let mut differences = vec![];
+    let card = |config: &SystemConfig| config.video_card_name().unwrap_or_default().to_string();
+    if card(expected) != card(found) {
+        differences.push(Difference { tag: "videoCardDescription".into(), expected: card(expected), found: card(found) });
+    }
+    for (ours, theirs) in expected.settings().iter().zip(found.settings()) {
+        let (ours_text, theirs_text) = (xml::setting_text(ours), xml::setting_text(theirs));
+        if ours_text != theirs_text {
+            differences.push(Difference { tag: ours.tag.clone(), expected: ours_text, found: theirs_text });
+        }
+    }
+    for ours in expected.defaults() {
+        // Compared as they'd be written, the fullscreen size follows the sliders
+        let derived = ours.section.and_then(|section| xml::derived_value(section, &ours.tag, expected.settings()));
+        let ours_text = derived.as_ref().unwrap_or(&ours.value).as_str().to_string();
+        let theirs = found.defaults().iter().find(|element| element.tag == ours.tag && element.section == ours.section);
+        let theirs_text = theirs.map_or(String::new(), |element| element.value.as_str().to_string());
+        if ours_text != theirs_text {
+            differences.push(Difference { tag: ours.tag.clone(), expected: ours_text, found: theirs_text });
+        }
+    }
+    differences
+}
+
+fn is_reset(expected: &SystemConfig, found: &SystemConfig) -> bool {
+    if expected.video_card_name() != found.video_card_name() {
+        return true;
+    }
+    let defaults = SystemConfig::new();
+    let changed_by_us: Vec<usize> = (0..expected.settings().len())
+        .filter(|i| xml::setting_text(&expected.settings()[*i]) != xml::setting_text(&defaults.settings()[*i]))
+        .collect();
+    !changed_by_us.is_empty() && changed_by_us.iter()
+        .all(|i| xml::setting_text(&found.settings()[*i]) == xml::setting_text(&defaults.settings()[*i]))
+}
+
+/// Writes the profile back over the file, taking the card name the game wrote so it isn't reset again.
+/// The profile becomes the file as written, derived values included, so writing it isn't seen as a change.
+/// Returns the backup of the replaced file, `read_only` protects the new one.
+pub fn reapply(profile: &mut SystemConfig, found: Option<&SystemConfig>, path: &Path, read_only: bool) -> Result<Option<PathBuf>> {
+    let mut config = profile.clone();
+    if let Some(name) = found.and_then(SystemConfig::video_card_name) {
+        config = config.video_card(name);
+    }
+    let backup = xml::backup_file(path)?;
+    if read_only {
+        config.write_file_read_only(path)?;
+    } else {
+        config.write_file(path)?;
+    }
+    *profile = SystemConfig::read_file(path)?;
+    Ok(backup)
+}
+
+/// Waits for changes of one file.
+pub struct Watcher {
+    path: PathBuf,
+    interval: Duration,
+    // Modification time and size last seen when polling
+    last: Option<(SystemTime, u64)>,
+    #[cfg(target_os = "linux")]
+    inotify: Option<inotify::Inotify>,
+}
+
+impl Watcher {
+    /// Watches with inotify when it's available, by polling every `interval` otherwise.
+    pub fn new(path: &Path, interval: Duration) -> Self {
+        #[cfg(target_os = "linux")]
+        let watcher = Self { inotify: inotify::Inotify::new(path), ..Self::polling(path, interval) };
+        #[cfg(not(target_os = "linux"))]
+        let watcher = Self::polling(path, interval);
+        watcher
+    }
+
+    /// Polls every `interval`, for file systems that don't report changes.
+    pub fn polling(path: &Path, interval: Duration) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            interval,
+            last: file_state(path),
+            #[cfg(target_os = "linux")]
+            inotify: None,
+        }
+    }
+
+    pub fn uses_inotify(&self) -> bool {
+        #[cfg(target_os = "linux")]
+        let uses = self.inotify.is_some();
+        #[cfg(not(target_os = "linux"))]
+        let uses = false;
+        uses
+    }
+
+    /// Blocks until the file is written, replaced or removed, false when `timeout` passed first.
+    pub fn wait(&mut self, timeout: Option<Duration>) -> Result<bool> {
+        #[cfg(target_os = "linux")]
+        if let Some(inotify) = &self.inotify {
+            let changed = inotify.wait(timeout)?;
+            if changed {
+                std::thread::sleep(SETTLE);
+                inotify.drain();
+            }
+            return Ok(changed);
+        }
+        let start = Instant::now();
+        loop {
+            let state = file_state(&self.path);
+            if state != self.last {
+                self.last = state;
+                std::thread::sleep(SETTLE);
+                self.last = file_state(&self.path);
+                return Ok(true);
+            }
+            if timeout.is_some_and(|timeout| start.elapsed() >= timeout) {
+                return Ok(false);
+            }
+            std::thread::sleep(self.interval);
+        }
+    }
+}
+
+fn file_state(path: &Path) -> Option<(SystemTime, u64)> {
+    let metadata = std::fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+#[cfg(target_os = "linux")]
+mod inotify {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::Path;
+    use std::time::Duration;
+    use crate::error::{Error, Result};
+
+    #[allow(non_camel_case_types)]
+    type void = std::ffi::c_void;
+
+    const IN_NONBLOCK:    i32 = 0o4000;
+    const IN_CLOEXEC:     i32 = 0o2000000;
+    const IN_CLOSE_WRITE: u32 = 0x008;
+    const IN_MOVED_FROM:  u32 = 0x040;
+    const IN_MOVED_TO:    u32 = 0x080;
+    const IN_DELETE:      u32 = 0x200;
+    const POLLIN:         i16 = 1;
+    // struct inotify_event without the name that follows it
+    const EVENT_SIZE:   usize = 16;
+
+    #[repr(C)]
+    struct PollFd {
+        file_descriptor: i32,  // int   fd
+        request_events:  i16,  // short events
+        return_events:   i16,  // short revents
+    }
+
+    extern "C" {
+        fn inotify_init1(flags: i32) -> i32;
+        fn inotify_add_watch(fd: i32, path: *const std::ffi::c_char, mask: u32) -> i32;
+        fn read(fd: i32, buffer: *mut void, buffer_size: usize) -> isize;
+        fn poll(fds: *mut PollFd, fds_count: std::ffi::c_ulong, timeout: i32) -> i32;
+        fn close(fd: i32) -> i32;
+    }
+
+    // Watches the directory, the game and atomic writes replace the file rather than write into it
+    pub(super) struct Inotify {
+        fd: i32,
+        name: Vec<u8>,
+    }
+
+    impl Inotify {
+        pub(super) fn new(path: &Path) -> Option<Self> {
+            let dir = path.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new("."));
+            let dir = CString::new(dir.as_os_str().as_bytes()).ok()?;
+            let name = path.file_name()?.as_bytes().to_vec();
+            let fd = unsafe { inotify_init1(IN_NONBLOCK | IN_CLOEXEC) };
+            if fd < 0 {
+                return None;
+            }
+            let watch = unsafe { inotify_add_watch(fd, dir.as_ptr(), IN_CLOSE_WRITE | IN_MOVED_FROM | IN_MOVED_TO | IN_DELETE) };
+            if watch < 0 {
+                unsafe { close(fd) };
+                return None;
+            }
+            Some(Self { fd, name })
+        }
+
+        pub(super) fn wait(&self, timeout: Option<Duration>) -> Result<bool> {
+            let timeout = timeout.map_or(-1, |timeout| timeout.as_millis().min(i32::MAX as u128) as i32);
+            let mut pollfd = PollFd { file_descriptor: self.fd, request_events: POLLIN, return_events: 0 };
+            loop {
+                let ready = unsafe { poll(&mut pollfd, 1, timeout) };
+                if ready < 0 {
+                    // A signal like a terminal resize interrupts the wait, it isn't a failure
+                    let err = std::io::Error::last_os_error();
+                    if err.kind() == std::io::ErrorKind::Interrupted {
+                        continue;
+                    }
+                    return Err(Error::Io(err));
+                }
+                if ready == 0 {
+                    return Ok(false);
+                }
+                // Events for other files in the directory are skipped, waiting again restarts the timeout
+                if self.drain() {
+                    return Ok(true);
+                }
+            }
+        }
+
+        // Reads every pending event, true if one was about the watched file
+        pub(super) fn drain(&self) -> bool {
+            let mut matched = false;
+            let mut buffer = [0u8; 4096];
+            loop {
+                let length = unsafe { read(self.fd, buffer.as_mut_ptr() as *mut void, buffer.len()) };
+                if length <= 0 {
+                    return matched;
+                }
+                let mut offset = 0;
+                while offset + EVENT_SIZE <= length as usize {
+                    let name_length = u32::from_ne_bytes(buffer[offset + 12..offset + 16].try_into().unwrap_or_default()) as usize;
+                    let name = &buffer[offset + EVENT_SIZE..(offset + EVENT_SIZE + name_length).min(length as usize)];
+                    let name = name.split(|byte| *byte == 0).next().unwrap_or_default();
+                    matched |= name == self.name.as_slice();
+                    offset += EVENT_SIZE + name_length;
+                }
+            }
+        }
+    }
+
+    impl Drop for Inotify {
+        fn drop(&mut self) {
+            unsafe { close(self.fd) };
+        }
+    }
+}
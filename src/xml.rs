@@ -0,0 +1,624 @@
+//! Reading and writing of `system.xml`.
+//!
+//! The reader is deliberately small, it understands the subset of XML the game writes:
+//! a declaration, nested elements, `value` attributes and text content.
+
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use xmlwriter::{Options, XmlWriter};
+use crate::error::{self, Error, Result};
+use crate::settings::{Setting, XMLSection};
+use crate::settings::SettingType::{Indexed, Level, Multiplier, OnHalfOff, OnOff, Range, RefreshRate, Slider};
+use crate::settings::XMLSection::{AdvancedGraphics, Graphics, Video};
+use crate::validation;
+
+pub const ROOT_TAG: &str = "rage__fwuiSystemSettingsCollection";
+pub const VERSION: &str = "37";
+// Written from the refresh rate setting, which is stored under the numerator's tag
+const DENOMINATOR_TAG: &str = "refreshRateDenominator";
+/// Video elements pointing into the monitor's mode list only the game knows,
+/// they belong to the resolution and to the refresh rate they were written with.
+pub const RESOLUTION_INDEX_TAGS: &[&str] = &["resolutionIndexWindowed", "resolutionIndex"];
+pub const REFRESH_RATE_INDEX_TAG: &str = "refreshRateIndex";
+
+/// Value of a leaf element, either `<tag value="..."/>` or `<tag>...</tag>`.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Value {
+    Attribute(String),
+    Text(String),
+    Empty,
+}
+
+impl Value {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Value::Attribute(value) | Value::Text(value) => value,
+            Value::Empty => "",
+        }
+    }
+}
+
+/// Leaf element together with the section it was found in and its position in the file.
+#[derive(Clone, Debug)]
+pub struct Element {
+    pub section: Option<XMLSection>,
+    pub tag: String,
+    pub value: Value,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Element {
+    pub fn new(section: Option<XMLSection>, tag: &str, value: Value) -> Self {
+        Self { section, tag: tag.into(), value, line: 0, column: 0 }
+    }
+}
+
+/// Flattened `system.xml`, leaf elements in document order.
+pub struct Document {
+    pub root: String,
+    pub elements: Vec<Element>,
+}
+
+impl Document {
+    pub fn read_file(path: &Path) -> Result<Self> {
+        let content = read_to_string(path)?;
+        Self::parse(&content)
+    }
+
+    pub fn parse(content: &str) -> Result<Self> {
+        Parser::new(content).parse()
+    }
+
+    /// First element with the given tag, `None` section means a top-level element.
+    pub fn find(&self, section: Option<XMLSection>, tag: &str) -> Option<&Element> {
+        self.elements.iter().find(|element| element.section == section && element.tag == tag)
+    }
+
+    pub fn version(&self) -> Option<&str> {
+        self.find(None, "version").map(|element| element.value.as_str())
+    }
+
+    pub fn video_card(&self) -> Option<&str> {
+        let element = self.find(None, "videoCardDescription")?;
+        let card_name = element.value.as_str().trim();
+        if card_name.is_empty() {
+            return None;
+        }
+        Some(card_name)
+    }
+
+    /// Copies the values of hidden elements found in the document, missing ones are left untouched.
+    pub fn apply_to_defaults(&self, defaults: &mut [Element]) {
+        for default in defaults.iter_mut() {
+            if let Some(element) = self.find(default.section, &default.tag) {
+                default.value = element.value.clone();
+            }
+        }
+    }
+
+    /// Element holding a catalog setting, a refresh rate is joined with its denominator as `N/D`.
+    pub fn setting_element(&self, setting: &Setting) -> Option<Element> {
+        let mut element = self.find(Some(setting.section), &setting.tag)?.clone();
+        if let RefreshRate(..) = setting.setting_type {
+            if let Some(denominator) = self.find(Some(setting.section), DENOMINATOR_TAG) {
+                element.value = Value::Attribute(format!("{}/{}", element.value.as_str(), denominator.value.as_str()));
+            }
+        }
+        Some(element)
+    }
+
+    /// Copies the values of catalog settings found in the document, missing ones are left untouched.
+    /// Values out of the setting's range are rejected with the line they're on.
+    pub fn apply_to(&self, settings: &mut [Setting]) -> Result<()> {
+        for setting in settings.iter_mut() {
+            if let Some(element) = self.setting_element(setting) {
+                read_setting(setting, &element)?;
+                if let Some(message) = validation::check_read(setting) {
+                    return Err(Error::Validation(format!("line {}: {}: {message}", element.line, setting.tag)));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Reads a file, reporting invalid UTF-8 as a parse error at the offending position.
+pub fn read_to_string(path: &Path) -> Result<String> {
+    let bytes = std::fs::read(path)?;
+    match String::from_utf8(bytes) {
+        Ok(content) => Ok(content),
+        Err(err) => {
+            let valid_up_to = err.utf8_error().valid_up_to();
+            let valid = String::from_utf8_lossy(&err.as_bytes()[..valid_up_to]).into_owned();
+            Err(Error::parse_at(&valid, valid_up_to, "file is not valid UTF-8"))
+        }
+    }
+}
+
+pub(crate) fn read_setting(setting: &mut Setting, element: &Element) -> Result<()> {
+    let raw = element.value.as_str();
+    let invalid = || Error::Parse {
+        line: element.line,
+        column: element.column,
+        message: format!("invalid value '{raw}' for {}", setting.tag),
+    };
+    match &mut setting.setting_type {
+        Level(selected_index, selectables, _) => {
+            let position = selectables.iter().position(|option| option.config_name == raw);
+            *selected_index = position.ok_or_else(invalid)?;
+        }
+        OnOff(enabled) => {
+            *enabled = match raw {
+                "true" => true,
+                "false" => false,
+                _ => return Err(invalid()),
+            };
+        }
+        OnHalfOff(value) | Multiplier(value, _) | Slider(value, _, _) | Indexed(value, _) | Range(value, _, _, _) => {
+            *value = raw.parse().map_err(|_| invalid())?;
+        }
+        RefreshRate(numerator, denominator) => {
+            let (whole, rest) = raw.split_once('/').unwrap_or((raw, "1"));
+            *numerator = whole.parse().map_err(|_| invalid())?;
+            *denominator = rest.parse().map_err(|_| invalid())?;
+        }
+    }
+    Ok(())
+}
+
+struct Parser<'a> {
+    content: &'a str,
+    position: usize,
+}
+
+struct OpenElement {
+    tag: String,
+    value_attribute: Option<String>,
+    text: String,
+    has_children: bool,
+    offset: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(content: &'a str) -> Self {
+        Self { content, position: 0 }
+    }
+
+    fn error(&self, offset: usize, message: &str) -> Error {
+        Error::parse_at(self.content, offset, message)
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.content[self.position..]
+    }
+
+    fn parse(mut self) -> Result<Document> {
+        let mut root: Option<String> = None;
+        let mut stack: Vec<OpenElement> = vec![];
+        let mut elements = vec![];
+        while self.position < self.content.len() {
+            let rest = self.rest();
+            if !rest.starts_with('<') {
+                let text_length = rest.find('<').unwrap_or(rest.len());
+                let text = &rest[..text_length];
+                match stack.last_mut() {
+                    Some(open) => open.text.push_str(&unescape(text)),
+                    None if text.trim().is_empty() => {}
+                    None => return Err(self.error(self.position, "text outside of the root element")),
+                }
+                self.position += text_length;
+                continue;
+            }
+            if rest.starts_with("<?") {
+                self.skip_past("?>")?;
+                continue;
+            }
+            if rest.starts_with("<!--") {
+                self.skip_past("-->")?;
+                continue;
+            }
+            if rest.starts_with("</") {
+                let offset = self.position;
+                let end = self.skip_past(">")?;
+                let tag = self.content[offset + 2..end].trim();
+                let Some(open) = stack.pop() else {
+                    return Err(self.error(offset, &format!("unexpected closing tag </{tag}>")));
+                };
+                if open.tag != tag {
+                    return Err(self.error(offset, &format!("expected </{}>, found </{tag}>", open.tag)));
+                }
+                if !open.has_children {
+                    elements.push(self.leaf(&stack, open));
+                }
+                continue;
+            }
+
+            let offset = self.position;
+            let end = self.skip_past(">")?;
+            let mut inner = &self.content[offset + 1..end];
+            let self_closing = inner.ends_with('/');
+            if self_closing {
+                inner = &inner[..inner.len() - 1];
+            }
+            let open = self.open_element(inner, offset)?;
+            if stack.is_empty() {
+                if root.is_some() {
+                    return Err(self.error(offset, "more than one root element"));
+                }
+                root = Some(open.tag.clone());
+            }
+            if let Some(parent) = stack.last_mut() {
+                parent.has_children = true;
+            }
+            if self_closing {
+                if !stack.is_empty() {
+                    elements.push(self.leaf(&stack, open));
+                }
+            } else {
+                stack.push(open);
+            }
+        }
+        if let Some(open) = stack.last() {
+            return Err(self.error(open.offset, &format!("<{}> is never closed", open.tag)));
+        }
+        let Some(root) = root else {
+            return Err(self.error(0, "no root element"));
+        };
+        Ok(Document { root, elements })
+    }
+
+    // Moves past the terminator, returning the offset where it starts
+    fn skip_past(&mut self, terminator: &str) -> Result<usize> {
+        let Some(length) = self.rest().find(terminator) else {
+            return Err(self.error(self.position, &format!("missing '{terminator}'")));
+        };
+        let end = self.position + length;
+        self.position = end + terminator.len();
+        Ok(end)
+    }
+
+    fn open_element(&self, inner: &str, offset: usize) -> Result<OpenElement> {
+        let tag_length = inner.find(char::is_whitespace).unwrap_or(inner.len());
+        let tag = &inner[..tag_length];
+        if tag.is_empty() {
+            return Err(self.error(offset, "element without a name"));
+        }
+        let mut value_attribute = None;
+        let mut attributes = inner[tag_length..].trim();
+        while !attributes.is_empty() {
+            let Some(equals) = attributes.find('=') else {
+                return Err(self.error(offset, &format!("malformed attribute in <{tag}>")));
+            };
+            let name = attributes[..equals].trim();
+            let after = attributes[equals + 1..].trim_start();
+            let Some(quote) = after.chars().next().filter(|c| *c == '"' || *c == '\'') else {
+                return Err(self.error(offset, &format!("unquoted attribute '{name}' in <{tag}>")));
+            };
+            let Some(value_length) = after[1..].find(quote) else {
+                return Err(self.error(offset, &format!("unterminated attribute '{name}' in <{tag}>")));
+            };
+            if name == "value" {
+                value_attribute = Some(unescape(&after[1..1 + value_length]));
+            }
+            attributes = after[value_length + 2..].trim_start();
+        }
+        Ok(OpenElement {
+            tag: tag.to_string(),
+            value_attribute,
+            text: String::new(),
+            has_children: false,
+            offset,
+        })
+    }
+
+    fn leaf(&self, stack: &[OpenElement], open: OpenElement) -> Element {
+        let (line, column) = error::line_and_column(self.content, open.offset);
+        let section = match stack {
+            [_root, section, ..] => XMLSection::from_tag(&section.tag),
+            _ => None,
+        };
+        let text = open.text.trim();
+        let value = match open.value_attribute {
+            Some(value) => Value::Attribute(value),
+            None if text.is_empty() => Value::Empty,
+            None => Value::Text(text.to_string()),
+        };
+        Element { section, tag: open.tag, value, line, column }
+    }
+}
+
+fn unescape(text: &str) -> String {
+    if !text.contains('&') {
+        return text.to_string();
+    }
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+/// Serializes the settings into a complete `system.xml` document with the default hidden values.
+pub fn to_xml(settings: &[Setting], card_name: &str) -> Result<String> {
+    to_xml_with_defaults(settings, &default_elements(), card_name)
+}
+
+/// Serializes the settings, `defaults` are the elements the catalog doesn't manage.
+/// Invalid settings are rejected before anything is written.
+pub fn to_xml_with_defaults(settings: &[Setting], defaults: &[Element], card_name: &str) -> Result<String> {
+    validation::validate(settings)?;
+    let opt = Options {
+        use_single_quote: false, // RDR2 has double quote
+        ..Options::default()
+    };
+    let mut xml = XmlWriter::new(opt);
+    xml.write_declaration();
+
+    xml.start_element(ROOT_TAG);
+
+    xml.start_element("version");
+    xml.write_attribute("value", VERSION);
+    xml.end_element();
+
+    xml.start_element("configSource");
+    xml.set_preserve_whitespaces(true);
+    xml.write_text("kSettingsConfig_Auto");
+    xml.end_element();
+    xml.set_preserve_whitespaces(false);
+
+    for section in XMLSection::ALL {
+        xml.start_element(section.tag());
+        write_default_section(section, defaults, settings, &mut xml);
+        write_options_section(section, settings, &mut xml);
+        xml.end_element();
+    }
+
+    xml.start_element("videoCardDescription");
+    xml.set_preserve_whitespaces(true);
+    // Config will be reset if video card desc doesn't match, just why
+    xml.write_text(&escape_ampersands(card_name));
+    xml.end_element();
+    xml.set_preserve_whitespaces(false);
+
+    Ok(xml.end_document())
+}
+
+/// Validates the settings and writes them to `path`, see [`write_atomic`].
+pub fn write_file(path: &Path, settings: &[Setting], defaults: &[Element], card_name: &str) -> Result<()> {
+    let content = to_xml_with_defaults(settings, defaults, card_name)?;
+    write_atomic(path, content.as_bytes())
+}
+
+/// Writes a temporary file next to `path` and renames it over `path`, so the game never reads
+/// half a config. A read-only `path` is made writable first, renaming over it fails on Windows.
+pub fn write_atomic(path: &Path, content: &[u8]) -> Result<()> {
+    if path.exists() {
+        set_read_only(path, false)?;
+    }
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let temporary = path.with_file_name(format!("{name}.tmp"));
+    let written = File::create(&temporary)
+        .and_then(|mut file| file.write_all(content).and_then(|()| file.sync_all()))
+        .and_then(|()| std::fs::rename(&temporary, path));
+    if written.is_err() {
+        let _ = std::fs::remove_file(&temporary);
+    }
+    Ok(written?)
+}
+
+/// Whether the file is read-only, which keeps the game from saving settings changed in its menus.
+pub fn is_read_only(path: &Path) -> Result<bool> {
+    Ok(std::fs::metadata(path)?.permissions().readonly())
+}
+
+/// Sets or clears read-only. On Unix only the owner's write bit is given back.
+pub fn set_read_only(path: &Path, read_only: bool) -> Result<()> {
+    let mut permissions = std::fs::metadata(path)?.permissions();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = permissions.mode();
+        permissions.set_mode(if read_only { mode & !0o222 } else { mode | 0o200 });
+    }
+    #[cfg(not(unix))]
+    permissions.set_readonly(read_only);
+    std::fs::set_permissions(path, permissions)?;
+    Ok(())
+}
+
+/// Copies an existing file to `<name>.<unix time>.bak` next to it, keeping its modification time
+/// so the copy still tells when those settings were put in place.
+pub fn backup_file(path: &Path) -> Result<Option<PathBuf>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let modified = std::fs::metadata(path)?.modified()?;
+    let seconds = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |duration| duration.as_secs());
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let mut backup = path.with_file_name(format!("{name}.{seconds}.bak"));
+    let mut count = 1;
+    while backup.exists() {
+        backup = path.with_file_name(format!("{name}.{seconds}-{count}.bak"));
+        count += 1;
+    }
+    // The copy takes the permissions along, backups needn't be protected
+    std::fs::copy(path, &backup)?;
+    set_read_only(&backup, false)?;
+    File::options().write(true).open(&backup)?.set_modified(modified)?;
+    Ok(Some(backup))
+}
+
+fn write_options_section(section: XMLSection, settings: &[Setting], xml: &mut XmlWriter) {
+    for setting in settings.iter() {
+        if setting.section != section {
+            continue;
+        }
+        write_value(&setting.tag, &setting_value(setting), xml);
+    }
+}
+
+/// Value of a catalog setting as it's written to the file.
+pub fn setting_value(setting: &Setting) -> Value {
+    match &setting.setting_type {
+        Level(index, selectables, _) => Value::Text(selectables[*index].config_name.clone()),
+        OnOff(on) => {
+            let boolean = if *on { "true" } else { "false" };
+            Value::Attribute(boolean.into())
+        }
+        Multiplier(value, _) => Value::Attribute(value.to_string()),
+        OnHalfOff(vsync) => Value::Attribute(vsync.to_string()),
+        Slider(pixels, _, _) => Value::Attribute(pixels.to_string()),
+        Indexed(value, _) | Range(value, _, _, _) => Value::Attribute(value.to_string()),
+        RefreshRate(numerator, _) => Value::Attribute(numerator.to_string()),
+    }
+}
+
+/// Value as [`read_setting`] takes it back, a refresh rate keeps its denominator as `N/D`.
+pub fn setting_text(setting: &Setting) -> String {
+    match &setting.setting_type {
+        RefreshRate(numerator, denominator) => format!("{numerator}/{denominator}"),
+        _ => setting_value(setting).as_str().into(),
+    }
+}
+
+/// Elements the selector doesn't manage, written with fixed values before the catalog settings.
+pub fn default_elements() -> Vec<Element> {
+    let mut defaults = Defaults { section: Graphics, elements: vec![] };
+    default_graphics(&mut defaults);
+    defaults.section = Video;
+    default_video(&mut defaults);
+    defaults.section = AdvancedGraphics;
+    default_advanced_graphics(&mut defaults);
+    defaults.elements
+}
+
+struct Defaults {
+    section: XMLSection,
+    elements: Vec<Element>,
+}
+
+impl Defaults {
+    fn attribute(&mut self, tag: &str, value: &str) {
+        self.elements.push(Element::new(Some(self.section), tag, Value::Attribute(value.into())));
+    }
+
+    fn text(&mut self, tag: &str, text: &str) {
+        self.elements.push(Element::new(Some(self.section), tag, Value::Text(text.into())));
+    }
+}
+
+fn default_graphics(defaults: &mut Defaults) {
+    defaults.attribute("graphicsQualityPreset", "0.5");
+    defaults.attribute("hdrSettingsMigrated", "true");
+}
+
+fn default_video(defaults: &mut Defaults) {
+    defaults.attribute("resolutionIndexWindowed", "0");
+    defaults.attribute("resolutionIndex", "1");
+
+    defaults.attribute("screenWidth", "1240");
+    defaults.attribute("screenHeight", "720");
+
+    defaults.attribute("refreshRateIndex", "0");
+    defaults.attribute(DENOMINATOR_TAG, "1");
+
+    defaults.attribute("pauseOnFocusLoss", "false"); // make selectable
+    defaults.attribute("constrainMousePointer", "false"); // make selectable
+}
+
+fn default_advanced_graphics(defaults: &mut Defaults) {
+    defaults.attribute("locked", "false");
+    defaults.attribute("motionBlurLimit", "16.0");
+    defaults.attribute("waterSimulationQuality", "3"); // make selectable
+    defaults.text("waterLightingQuality", "kSettingLevel_Ultra"); //make selectable
+    defaults.attribute("maxTexUpgradesPerFrame", "5");
+    // check
+    defaults.text("shadowGrassShadows", "kSettingLevel_High");
+    defaults.attribute("shadowParticleShadows", "true");
+    defaults.attribute("shadowLongShadows", "true");
+    defaults.attribute("directionalShadowsAlpha", "false");
+    defaults.attribute("worldHeightShadowQuality", "1.0");
+    defaults.attribute("directionalScreenSpaceShadowQuality", "1.0");
+    defaults.attribute("ambientMaskVolumesHighPrecision", "true");
+    defaults.text("scatteringVolumeQuality", "kSettingLevel_High");
+    defaults.text("volumetricsRaymarchQuality", "kSettingLevel_High");
+    defaults.text("volumetricsLightingQuality", "kSettingLevel_High");
+    defaults.attribute("volumetricsRaymarchResolutionUnclamped", "true");
+    defaults.text("terrainShadowQuality", "kSettingLevel_Ultra");
+    defaults.attribute("ssaoFullScreenEnabled", "false");
+    defaults.attribute("ssaoType", "0");
+    defaults.attribute("ssdoSampleCount", "4");
+    defaults.attribute("ssdoUseDualRadii", "false");
+    defaults.text("ssdoResolution", "kSettingLevel_Low");
+    defaults.attribute("ssdoTAABlendEnabled", "true");
+    defaults.attribute("ssroSampleCount", "2");
+    defaults.attribute("probeRelightEveryFrame", "false");
+    defaults.attribute("reflectionMSAA", "0");
+    defaults.attribute("lodScale", "1.0");
+    defaults.attribute("grassLod", "3.0");
+    defaults.attribute("pedLodBias", "0");
+    defaults.attribute("vehicleLodBias", "0");
+    defaults.attribute("sharpenIntensity", "1");
+}
+
+fn write_default_section(section: XMLSection, defaults: &[Element], settings: &[Setting], xml: &mut XmlWriter) {
+    for element in defaults.iter().filter(|element| element.section == Some(section)) {
+        match derived_value(section, &element.tag, settings) {
+            Some(value) => write_value(&element.tag, &value, xml),
+            None => write_value(&element.tag, &element.value, xml),
+        }
+    }
+}
+
+/// Hidden value that has to agree with the catalog settings, `None` for independent ones.
+///
+/// The fullscreen size follows the windowed sliders so switching modes in-game keeps the resolution.
+/// The mode list indexes can't be derived, see [`SystemConfig::written_defaults`](crate::SystemConfig::written_defaults).
+pub fn derived_value(section: XMLSection, tag: &str, settings: &[Setting]) -> Option<Value> {
+    if section != Video {
+        return None;
+    }
+    let (width, height) = crate::vram::resolution(settings);
+    match tag {
+        "screenWidth" => Some(Value::Attribute(width.to_string())),
+        "screenHeight" => Some(Value::Attribute(height.to_string())),
+        DENOMINATOR_TAG => settings.iter().find_map(|setting| match setting.setting_type {
+            RefreshRate(_, denominator) => Some(Value::Attribute(denominator.to_string())),
+            _ => None,
+        }),
+        _ => None,
+    }
+}
+
+fn write_value(tag: &str, value: &Value, xml: &mut XmlWriter) {
+    match value {
+        Value::Attribute(value) => write_element(tag, value, xml),
+        Value::Text(text) => write_text_element(tag, text, xml),
+        Value::Empty => {
+            xml.start_element(tag);
+            xml.end_element();
+        }
+    }
+}
+
+fn write_element(name: &str, val: &str, xml: &mut XmlWriter) {
+    xml.start_element(name);
+    xml.write_attribute("value", &escape_ampersands(val));
+    xml.end_element();
+}
+
+fn write_text_element(name: &str, text: &str, xml: &mut XmlWriter) {
+    xml.start_element(name);
+    xml.set_preserve_whitespaces(true);
+    xml.write_text(&escape_ampersands(text));
+    xml.end_element();
+    xml.set_preserve_whitespaces(false);
+}
+
+// xmlwriter only escapes '<' in text and quotes in attributes
+fn escape_ampersands(text: &str) -> String {
+    text.replace('&', "&amp;")
+}
\ No newline at end of file
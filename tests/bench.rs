@@ -0,0 +1,76 @@
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use rdr2_gfx_selector::bench::{self, Benchmark};
+
+mod common;
+
+const REPORT: &str = "\
+Frames Per Second (Higher is better) Min, Max, Avg
+Pass 0, 32.110001, 112.534729, 72.938454
+Pass 1, 40.5, 98.2, 66.1
+Time in milliseconds(ms). (Lower is Better). Min, Max, Avg
+Pass 0, 8.886, 31.142, 13.710
+Pass 1, 10.18, 24.69, 15.13
+";
+
+fn at(seconds: u64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_secs(seconds)
+}
+
+fn write_at(path: &Path, content: &str, seconds: u64) {
+    std::fs::write(path, content).unwrap();
+    std::fs::File::options().write(true).open(path).unwrap().set_modified(at(seconds)).unwrap();
+}
+
+#[test]
+fn reports_read_per_pass() {
+    let passes = bench::parse(REPORT).unwrap();
+    assert_eq!(passes.len(), 2);
+    assert_eq!(passes[0].name, "Pass 0");
+    assert!((passes[0].fps.min - 32.11).abs() < 1e-4);
+    assert!((passes[1].frame_time.unwrap().avg - 15.13).abs() < 1e-9);
+
+    let Err(err) = bench::parse("Frames Per Second (Higher is better) Min, Max, Avg\nPass 0, 32.1, fast, 70\n") else {
+        panic!("a broken number should be reported");
+    };
+    assert!(err.to_string().contains("2:1"));
+    assert!(bench::parse("no results here\n").is_err());
+}
+
+#[test]
+fn runs_use_the_newest_config_written_before_them() {
+    let configs = vec![(PathBuf::from("system.xml"), at(100)), (PathBuf::from("profiles/high.xml"), at(200))];
+    assert_eq!(bench::settings_at(at(150), &configs), Some(PathBuf::from("system.xml")));
+    assert_eq!(bench::settings_at(at(200), &configs), Some(PathBuf::from("profiles/high.xml")));
+    assert_eq!(bench::settings_at(at(50), &configs), None);
+}
+
+#[test]
+fn benchmark_dir_is_read_oldest_first() {
+    let dir = common::temp_dir("bench-dir");
+    let benchmarks = dir.join(bench::BENCHMARKS_DIR);
+    std::fs::create_dir_all(&benchmarks).unwrap();
+    write_at(&benchmarks.join("later.txt"), REPORT, 3000);
+    write_at(&benchmarks.join("earlier.txt"), REPORT, 1000);
+    write_at(&benchmarks.join("broken.txt"), "nothing", 2000);
+    write_at(&dir.join("system.xml"), "", 500);
+    write_at(&dir.join("system.xml.bak"), "", 2500);
+
+    let (runs, broken) = bench::read_dir(&benchmarks).unwrap();
+    let names: Vec<_> = runs.iter().map(|run| run.path.file_name().unwrap().to_str().unwrap()).collect();
+    assert_eq!(names, ["earlier.txt", "later.txt"]);
+    assert_eq!(broken.len(), 1);
+
+    let configs = bench::config_files(&dir, &dir.join("profiles"));
+    assert_eq!(runs[0].settings_path(&configs), Some(dir.join("system.xml")));
+    assert_eq!(runs[1].settings_path(&configs), Some(dir.join("system.xml.bak")));
+    assert!((runs[0].avg_fps() - 69.519227).abs() < 1e-4);
+    assert!((Benchmark::read_file(&benchmarks.join("later.txt")).unwrap().min_fps() - 32.11).abs() < 1e-4);
+    common::remove_dir(&dir);
+}
+
+#[test]
+fn times_print_as_utc_dates() {
+    assert_eq!(bench::format_time(at(0)), "1970-01-01 00:00");
+    assert_eq!(bench::format_time(at(1_709_210_096)), "2024-02-29 12:34");
+}
@@ -0,0 +1,123 @@
+use rdr2_gfx_selector::calibration::{self, Calibration, Reading};
+use rdr2_gfx_selector::settings::{self, Setting, SettingType};
+use rdr2_gfx_selector::tune::{self, TuneOptions};
+use rdr2_gfx_selector::vram;
+
+fn truth() -> Calibration {
+    Calibration {
+        min_vram: 1600.0,
+        pixels_per_mb: 4000.0,
+        steps: vec![("textureQuality".into(), vec![200, 250, 800]), ("reflectionQuality".into(), vec![40, 100, 500])],
+    }
+}
+
+// Varied settings picked by a small LCG so the readings are the same on every run
+fn readings(model: &Calibration, count: usize) -> Vec<Reading> {
+    let resolutions = [(1280, 720), (1920, 1080), (2560, 1440), (3840, 2160), (1280, 800)];
+    let mut seed: u64 = 42;
+    let mut next = |limit: usize| {
+        seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        (seed >> 33) as usize % limit
+    };
+    (0..count).map(|_| {
+        let mut settings = settings::get_settings();
+        for setting in settings.iter_mut() {
+            if let SettingType::Level(selected_index, selectables, _) = &mut setting.setting_type {
+                *selected_index = next(selectables.len());
+            }
+        }
+        let (width, height) = resolutions[next(resolutions.len())];
+        set_slider(&mut settings, "screenWidthWindowed", width);
+        set_slider(&mut settings, "screenHeightWindowed", height);
+        let mut measured_with = settings.clone();
+        model.apply(&mut measured_with);
+        Reading { measured: vram::estimate_with(&measured_with, model), settings }
+    }).collect()
+}
+
+fn set_slider(settings: &mut [Setting], tag: &str, pixels: usize) {
+    let index = settings::get_setting_index_by_tag(settings, tag).unwrap();
+    if let SettingType::Slider(value, _, _) = &mut settings[index].setting_type {
+        *value = pixels;
+    }
+}
+
+fn steps_of<'a>(calibration: &'a Calibration, tag: &str) -> &'a [usize] {
+    &calibration.steps.iter().find(|(existing, _)| existing == tag).unwrap().1
+}
+
+#[test]
+fn fit_recovers_the_model_behind_the_readings() {
+    let fit = calibration::fit(&readings(&truth(), 200), &Calibration::builtin()).unwrap();
+    assert!(fit.rmse < 1.0, "rmse {}", fit.rmse);
+    assert!((fit.calibration.min_vram - 1600.0).abs() < 1.0);
+    assert!((fit.calibration.pixels_per_mb - 4000.0).abs() < 10.0);
+    assert_eq!(steps_of(&fit.calibration, "textureQuality"), [200, 250, 800]);
+    assert_eq!(steps_of(&fit.calibration, "reflectionQuality"), [40, 100, 500]);
+    assert_eq!(fit.r_squared.map(|r_squared| r_squared > 0.999), Some(true));
+}
+
+#[test]
+fn unvaried_settings_keep_their_cost() {
+    let mut readings = readings(&truth(), 100);
+    for reading in readings.iter_mut() {
+        let index = settings::get_setting_index_by_tag(&reading.settings, "shadowQuality").unwrap();
+        reading.settings[index].setting_type = settings::get_settings()[index].setting_type.clone();
+    }
+    let fit = calibration::fit(&readings, &Calibration::builtin()).unwrap();
+    assert_eq!(steps_of(&fit.calibration, "shadowQuality"), [72, 11, 333]);
+    assert!(fit.unconstrained.iter().any(|name| name.starts_with("shadowQuality")));
+}
+
+#[test]
+fn too_few_readings_are_rejected() {
+    assert!(calibration::fit(&readings(&truth(), 1), &Calibration::builtin()).is_err());
+}
+
+#[test]
+fn dataset_lines_read_back() {
+    let original = readings(&truth(), 3);
+    let content: String = original.iter()
+        .map(|reading| calibration::reading_line(reading.measured, &reading.settings) + "  # note\n")
+        .collect();
+    let parsed = calibration::parse_dataset(&content).unwrap();
+    assert_eq!(parsed.len(), 3);
+    for (parsed, original) in parsed.iter().zip(&original) {
+        assert_eq!(parsed.measured, original.measured);
+        assert_eq!(vram::estimate_vram(&parsed.settings), vram::estimate_vram(&original.settings));
+    }
+}
+
+#[test]
+fn override_file_reads_back() {
+    let written = truth().to_toml("test");
+    let parsed = Calibration::parse(&written).unwrap();
+    assert_eq!(parsed.min_vram, 1600.0);
+    assert_eq!(parsed.pixels_per_mb, 4000.0);
+    assert_eq!(steps_of(&parsed, "textureQuality"), [200, 250, 800]);
+    assert_eq!(steps_of(&parsed, "volumetricsQuality"), [16, 67, 111]);
+}
+
+#[test]
+fn override_rejects_unknown_options() {
+    assert!(Calibration::parse("[textureQuality]\nkSettingLevel_Epic = 5\n").is_err());
+    assert!(Calibration::parse("[fxaaEnabled]\n").is_err());
+}
+
+#[test]
+fn fitted_numbers_only_apply_where_passed() {
+    let settings = settings::get_settings();
+    let fitted = truth();
+    assert_eq!(vram::estimate_vram(&settings), Calibration::builtin().estimate(&settings));
+    let mut calibrated = settings.clone();
+    fitted.apply(&mut calibrated);
+    assert_eq!(fitted.estimate(&settings), vram::estimate_with(&calibrated, &fitted));
+    assert_ne!(fitted.estimate(&settings), vram::estimate_vram(&settings));
+
+    // The tuner fits the budget by the calibration it's given
+    let budget = 4000;
+    let mut tuned = settings.clone();
+    let estimate = tune::tune(&mut tuned, &TuneOptions::new(budget).calibration(fitted.clone())).unwrap();
+    assert_eq!(estimate, fitted.estimate(&tuned));
+    assert!(estimate <= budget as f64);
+}
@@ -0,0 +1,52 @@
+//! Helpers shared by the integration tests, each test file uses some of them.
+#![allow(dead_code)]
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use rdr2_gfx_selector::{xml, SystemConfig};
+
+pub const GPU: &str = "NVIDIA GeForce RTX 3070";
+
+/// Empty directory for one test, `name` has to be unique across the test files.
+pub fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("rdr2-{name}-{}", std::process::id()));
+    if dir.exists() {
+        remove_dir(&dir);
+    }
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Removes a test directory, Windows refuses to delete read-only files so they're made writable first.
+pub fn remove_dir(dir: &Path) {
+    for entry in std::fs::read_dir(dir).unwrap().flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            remove_dir(&path);
+        } else if xml::is_read_only(&path).unwrap() {
+            xml::set_read_only(&path, false).unwrap();
+        }
+    }
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+/// Defaults with a video card name, ready to be written.
+pub fn config() -> SystemConfig {
+    SystemConfig::new().video_card(GPU)
+}
+
+/// Home directory for one test with `config` installed as the game's `system.xml`.
+pub fn home_with(name: &str, config: &SystemConfig) -> PathBuf {
+    let home = temp_dir(name);
+    let settings = home.join("Documents").join("Rockstar Games").join("Red Dead Redemption 2").join("Settings");
+    std::fs::create_dir_all(&settings).unwrap();
+    config.write_file(&settings.join("system.xml")).unwrap();
+    home
+}
+
+/// The binary run in `home`, which it also takes as the user's home directory.
+pub fn command(home: &Path) -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_rdr2-gfx-selector"));
+    command.current_dir(home).env("HOME", home).env("USERPROFILE", home);
+    command
+}
@@ -0,0 +1,107 @@
+use rdr2_gfx_selector::settings::{self, SettingType};
+use rdr2_gfx_selector::{xml, Error, Result, SystemConfig};
+use common::{config, remove_dir, temp_dir};
+
+mod common;
+
+fn validation_error<T>(result: Result<T>) -> String {
+    match result {
+        Err(Error::Validation(message)) => message,
+        Err(err) => panic!("expected a validation error, got {err}"),
+        Ok(_) => panic!("expected a validation error"),
+    }
+}
+
+#[test]
+fn invalid_factors_are_rejected_when_writing() {
+    let dir = temp_dir("config-factors");
+    let path = dir.join("system.xml");
+    for config in [config().msaa(3), config().anisotropic_filtering(32)] {
+        let message = validation_error(config.to_xml());
+        assert!(message.contains("msaa") || message.contains("anisotropicFiltering"), "{message}");
+        validation_error(config.write_file(&path));
+        assert!(!path.exists());
+    }
+
+    for (msaa, anisotropic) in [(0, 0), (2, 2), (4, 4), (8, 8), (8, 16)] {
+        let config = config().msaa(msaa).anisotropic_filtering(anisotropic);
+        config.to_xml().unwrap();
+        config.write_file(&path).unwrap();
+        let written = SystemConfig::read_file(&path).unwrap();
+        assert_eq!(xml::setting_text(written.setting("msaa").unwrap()), msaa.to_string());
+        assert_eq!(xml::setting_text(written.setting("anisotropicFiltering").unwrap()), anisotropic.to_string());
+    }
+    remove_dir(&dir);
+}
+
+#[test]
+fn the_first_rejected_setter_is_reported() {
+    let message = validation_error(config().msaa(3).anisotropic_filtering(32).check());
+    assert!(message.contains("msaa"), "{message}");
+    let message = validation_error(SystemConfig::new().msaa(4).check());
+    assert_eq!(message, "video card name is missing");
+}
+
+#[test]
+fn out_of_range_values_are_rejected_when_reading() {
+    let written = config().to_xml().unwrap();
+    let content = written.replacen("<adapterIndex value=\"0\"/>", "<adapterIndex value=\"9\"/>", 1);
+    assert_ne!(content, written);
+    let line = content.lines().position(|line| line.contains("adapterIndex")).unwrap() + 1;
+    let document = xml::Document::parse(&content).unwrap();
+    let message = validation_error(SystemConfig::from_document(&document));
+    assert!(message.starts_with(&format!("line {line}: adapterIndex: 9 is not one of")), "{message}");
+
+    let content = written.replacen("<msaa value=\"0\"/>", "<msaa value=\"3\"/>", 1);
+    validation_error(SystemConfig::from_document(&xml::Document::parse(&content).unwrap()));
+}
+
+#[test]
+fn sizes_read_from_a_file_can_be_written_back() {
+    let content = config().to_xml().unwrap()
+        .replace("value=\"1024\"", "value=\"1366\"")
+        .replace("value=\"768\"", "value=\"766\"");
+    let read = SystemConfig::from_document(&xml::Document::parse(&content).unwrap()).unwrap();
+    assert_eq!(read.screen_resolution(), (1366, 766));
+    let written = read.msaa(4).to_xml().unwrap();
+    assert!(written.contains("<screenWidth value=\"1366\"/>"), "{written}");
+
+    let content = content.replacen("<screenHeightWindowed value=\"766\"/>", "<screenHeightWindowed value=\"0\"/>", 1);
+    validation_error(SystemConfig::from_document(&xml::Document::parse(&content).unwrap()));
+}
+
+#[test]
+fn mode_indexes_are_kept_until_the_mode_changes() {
+    let content = config().to_xml().unwrap()
+        .replacen("<resolutionIndex value=\"1\"/>", "<resolutionIndex value=\"7\"/>", 1)
+        .replacen("<refreshRateIndex value=\"0\"/>", "<refreshRateIndex value=\"2\"/>", 1);
+    let read = SystemConfig::from_document(&xml::Document::parse(&content).unwrap()).unwrap();
+    let kept = read.clone().msaa(4).to_xml().unwrap();
+    assert!(kept.contains("<resolutionIndex value=\"7\"/>"), "{kept}");
+    assert!(kept.contains("<refreshRateIndex value=\"2\"/>"), "{kept}");
+
+    let resized = read.clone().resolution(2560, 1440).to_xml().unwrap();
+    assert!(resized.contains("<resolutionIndex value=\"1\"/>"), "{resized}");
+    assert!(resized.contains("<refreshRateIndex value=\"2\"/>"), "{resized}");
+
+    let rate = read.refresh_rate(144, 1).to_xml().unwrap();
+    assert!(rate.contains("<resolutionIndex value=\"7\"/>"), "{rate}");
+    assert!(rate.contains("<refreshRateIndex value=\"0\"/>"), "{rate}");
+}
+
+#[test]
+fn invalid_values_are_still_displayed() {
+    let mut config = config();
+    for setting in config.settings_mut() {
+        match &mut setting.setting_type {
+            SettingType::Indexed(value, _) | SettingType::OnHalfOff(value) => *value = 9,
+            SettingType::Level(selected_index, ..) => *selected_index = 9,
+            _ => {}
+        }
+    }
+    let mut text = String::new();
+    for setting in config.settings() {
+        settings::append_setting_type(&mut text, &setting.setting_type);
+    }
+    assert!(text.contains("9 (invalid)"), "{text}");
+}
@@ -0,0 +1,80 @@
+use rdr2_gfx_selector::export::{self, Format};
+use rdr2_gfx_selector::{Api, DlssQuality, Error, Level};
+use common::config;
+
+mod common;
+
+#[test]
+fn imports_write_the_same_file() {
+    let configs = [
+        config(),
+        config().api(Api::Vulkan).msaa(4).texture_quality(Level::Ultra).dlss(Some(DlssQuality::Quality)).resolution(2560, 1440),
+        config().video_card("Name with \"quotes\" and \\ backslash").refresh_rate(5994, 100),
+    ];
+    for config in configs {
+        for format in [Format::Json, Format::Toml] {
+            let exported = export::export(&config, format);
+            let imported = export::import(&exported, format).unwrap();
+            assert_eq!(imported.to_xml().unwrap(), config.to_xml().unwrap(), "{format:?}");
+            assert_eq!(export::export(&imported, format), exported);
+        }
+    }
+}
+
+#[test]
+fn unterminated_strings_report_their_position() {
+    let json = "{\n  \"version\": \"37\",\n  \"videoCardDescription\": \"NVIDIA\n}";
+    let Err(Error::Parse { line, .. }) = export::import(json, Format::Json) else {
+        panic!("the string is never closed");
+    };
+    assert_eq!(line, 3);
+
+    let toml = "version = \"37\"\n\n[graphics]\nmsaa = \"4\n";
+    let Err(Error::Parse { line, message, .. }) = export::import(toml, Format::Toml) else {
+        panic!("the string is never closed");
+    };
+    assert_eq!(line, 4);
+    assert!(message.contains("unterminated string"), "{message}");
+}
+
+#[test]
+fn duplicate_keys_are_rejected() {
+    let duplicates = [
+        ("{\n  \"version\": \"37\",\n  \"version\": \"37\"\n}", Format::Json, "version"),
+        ("{\"graphics\": {\"msaa\": \"4\",\n\"msaa\": \"0\"}}", Format::Json, "msaa"),
+        ("{\"graphics\": {},\n\"graphics\": {}}", Format::Json, "graphics"),
+        ("[graphics]\nmsaa = \"4\"\nmsaa = \"0\"\n", Format::Toml, "msaa"),
+    ];
+    for (content, format, key) in duplicates {
+        let Err(Error::Parse { line, message, .. }) = export::import(content, format) else {
+            panic!("{key} appears twice in {content}");
+        };
+        assert_eq!(message, format!("duplicate key '{key}'"));
+        assert!(line > 1, "{content}");
+    }
+}
+
+#[test]
+fn unknown_keys_are_rejected() {
+    let toml = "version = \"37\"\nresolution = \"1080p\"\n";
+    let Err(Error::Validation(message)) = export::import(toml, Format::Toml) else {
+        panic!("the key is unknown");
+    };
+    assert_eq!(message, "unknown key 'resolution'");
+
+    let json = "{\"graphics\": {\"rayTracing\": \"1\"}}";
+    let Err(Error::Validation(message)) = export::import(json, Format::Json) else {
+        panic!("the key is unknown");
+    };
+    assert_eq!(message, "unknown key 'graphics.rayTracing'");
+}
+
+#[test]
+fn mode_indexes_survive_a_round_trip() {
+    let exported = export::export(&config().resolution(2560, 1440), Format::Toml);
+    let exported = exported.replacen("resolutionIndex = \"1\"", "resolutionIndex = \"7\"", 1);
+    assert!(exported.contains("resolutionIndex = \"7\""), "{exported}");
+    let imported = export::import(&exported, Format::Toml).unwrap();
+    assert!(imported.to_xml().unwrap().contains("<resolutionIndex value=\"7\"/>"));
+    assert!(export::export(&imported, Format::Toml).contains("resolutionIndex = \"7\""));
+}
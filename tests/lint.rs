@@ -0,0 +1,109 @@
+use std::process::Command;
+use rdr2_gfx_selector::calibration::Calibration;
+use rdr2_gfx_selector::lint::{self, Finding, Severity};
+use rdr2_gfx_selector::xml::Document;
+use common::{config, remove_dir, temp_dir};
+
+mod common;
+
+fn written() -> String {
+    config().to_xml().unwrap()
+}
+
+fn lint(content: &str) -> Vec<Finding> {
+    lint::lint_document(&Document::parse(content).unwrap(), 8192, &Calibration::builtin())
+}
+
+// The only finding with the code, checked against the line the edit was made on
+fn single<'a>(findings: &'a [Finding], code: &str) -> &'a Finding {
+    let matching: Vec<_> = findings.iter().filter(|finding| finding.code == code).collect();
+    assert_eq!(matching.len(), 1, "{findings:?}");
+    matching[0]
+}
+
+fn line_of(content: &str, needle: &str) -> usize {
+    content.lines().position(|line| line.contains(needle)).unwrap() + 1
+}
+
+#[test]
+fn written_files_are_clean() {
+    let findings = lint(&written());
+    assert!(findings.is_empty(), "{findings:?}");
+}
+
+#[test]
+fn duplicates_are_errors() {
+    let content = written().replacen("<msaa value=\"0\"/>", "<msaa value=\"0\"/>\n<msaa value=\"4\"/>", 1);
+    let findings = lint(&content);
+    let finding = single(&findings, "duplicate");
+    assert_eq!(finding.severity, Severity::Error);
+    assert_eq!(finding.tag.as_deref(), Some("msaa"));
+    assert_eq!(finding.line, line_of(&content, "<msaa value=\"4\"/>"));
+}
+
+#[test]
+fn unknown_tags_are_warnings() {
+    let content = written().replacen("<msaa value=\"0\"/>", "<msaa value=\"0\"/>\n<rayTracing value=\"1\"/>", 1);
+    let findings = lint(&content);
+    let finding = single(&findings, "unknown-tag");
+    assert_eq!(finding.severity, Severity::Warning);
+    assert_eq!(finding.line, line_of(&content, "rayTracing"));
+    assert!(!lint::has_errors(&findings));
+}
+
+#[test]
+fn invalid_values_are_errors() {
+    let content = written().replacen("<msaa value=\"0\"/>", "<msaa value=\"3\"/>", 1);
+    let findings = lint(&content);
+    let finding = single(&findings, "invalid-value");
+    assert_eq!(finding.severity, Severity::Error);
+    assert_eq!(finding.line, line_of(&content, "<msaa value=\"3\"/>"));
+
+    let content = written().replacen("<msaa value=\"0\"/>", "<msaa value=\"four\"/>", 1);
+    assert_eq!(single(&lint(&content), "invalid-value").tag.as_deref(), Some("msaa"));
+}
+
+#[test]
+fn missing_settings_are_warnings() {
+    let content = written().replacen("<msaa value=\"0\"/>", "", 1);
+    let findings = lint(&content);
+    let finding = single(&findings, "missing-setting");
+    assert_eq!(finding.severity, Severity::Warning);
+    assert_eq!(finding.tag.as_deref(), Some("msaa"));
+    assert_eq!((finding.line, finding.column), (0, 0));
+}
+
+#[test]
+fn fullscreen_size_has_to_follow_the_windowed_one() {
+    let content = written();
+    let width = content.lines().find(|line| line.trim_start().starts_with("<screenWidth ")).unwrap().trim();
+    let content = content.replacen(width, "<screenWidth value=\"1234\"/>", 1);
+    let findings = lint(&content);
+    let finding = single(&findings, "inconsistent-resolution");
+    assert_eq!(finding.severity, Severity::Warning);
+    assert_eq!(finding.line, line_of(&content, "<screenWidth value=\"1234\"/>"));
+}
+
+#[test]
+fn sizes_off_the_picker_steps_are_warnings() {
+    let content = config().resolution(1366, 768).to_xml().unwrap();
+    let findings = lint(&content);
+    assert!(findings.iter().all(|finding| finding.code == "uncommon-size" && finding.severity == Severity::Warning), "{findings:?}");
+    assert_eq!(findings.iter().filter(|finding| finding.tag.as_deref() == Some("screenWidthWindowed")).count(), 1);
+    assert!(!lint::has_errors(&findings));
+}
+
+#[test]
+fn only_errors_fail_the_command() {
+    let dir = temp_dir("lint-status");
+    let status = |content: String| {
+        let path = dir.join("system.xml");
+        std::fs::write(&path, content).unwrap();
+        Command::new(env!("CARGO_BIN_EXE_rdr2-gfx-selector")).arg("lint").arg(&path).output().unwrap().status
+    };
+    assert!(status(written()).success());
+    assert!(status(written().replacen("<msaa value=\"0\"/>", "", 1)).success());
+    assert_eq!(status(written().replacen("<msaa value=\"0\"/>", "<msaa value=\"3\"/>", 1)).code(), Some(1));
+    assert_eq!(status("<broken".into()).code(), Some(1));
+    remove_dir(&dir);
+}
@@ -0,0 +1,69 @@
+use std::path::Path;
+use rdr2_gfx_selector::perf::{self, PerfCosts, Sample};
+use rdr2_gfx_selector::{xml, Level, SystemConfig};
+use common::GPU;
+
+mod common;
+
+// 120 fps at the lowest settings, 10 per megapixel, MSAA 8 per step and 5 for ultra textures
+fn sample(config: SystemConfig) -> Sample {
+    let settings = config.settings().to_vec();
+    let (width, height) = (config.screen_resolution().0 as f64, config.screen_resolution().1 as f64);
+    let msaa = match config.setting("msaa").map(xml::setting_text).unwrap().as_str() {
+        "2" => 8.0,
+        "4" => 16.0,
+        _ => 0.0,
+    };
+    let textures = if xml::setting_text(config.setting("textureQuality").unwrap()) == "kSettingLevel_Ultra" { 5.0 } else { 0.0 };
+    Sample { fps: 120.0 - 10.0 * width * height / 1e6 - msaa - textures, settings }
+}
+
+fn samples() -> Vec<Sample> {
+    let mut samples = vec![];
+    for (width, height) in [(1920, 1080), (2560, 1440)] {
+        for msaa in [0, 2, 4] {
+            let config = SystemConfig::new().resolution(width, height).msaa(msaa);
+            samples.push(sample(config.clone()));
+            samples.push(sample(config.texture_quality(Level::Ultra)));
+        }
+    }
+    samples
+}
+
+#[test]
+fn fit_recovers_the_costs_behind_the_benchmarks() {
+    let fit = perf::fit(GPU, &samples()).unwrap();
+    let costs = &fit.costs;
+    assert!((costs.base_fps - 120.0).abs() < 0.1, "{}", costs.base_fps);
+    assert!((costs.fps_per_megapixel - 10.0).abs() < 0.1);
+    assert!(fit.rmse < 0.1);
+
+    let msaa4 = SystemConfig::new().msaa(4);
+    assert!((costs.setting_cost(msaa4.setting("msaa").unwrap()).unwrap() - 16.0).abs() < 0.1);
+    let ultra = SystemConfig::new().texture_quality(Level::Ultra);
+    assert!((costs.setting_cost(ultra.setting("textureQuality").unwrap()).unwrap() - 5.0).abs() < 0.1);
+    // Never varied, so not known
+    assert_eq!(costs.setting_cost(msaa4.setting("shadowQuality").unwrap()), None);
+    assert_eq!(costs.setting_cost(SystemConfig::new().msaa(8).setting("msaa").unwrap()), None);
+
+    assert!(perf::fit(GPU, &samples()[..1]).is_err());
+}
+
+#[test]
+fn costs_read_back() {
+    let costs = perf::fit(GPU, &samples()).unwrap().costs;
+    let read = PerfCosts::parse(&costs.to_toml("test")).unwrap();
+    assert_eq!(read.gpu, GPU);
+    assert_eq!(read.steps.len(), costs.steps.len());
+    assert!((read.base_fps - costs.base_fps).abs() < 0.01);
+
+    assert!(PerfCosts::parse("base_fps = 100\n").is_err());
+    assert!(PerfCosts::parse("gpu = \"X\"\n[msaa]\n3 = 1.0\n").is_err());
+    assert!(PerfCosts::parse("gpu = \"X\"\n[hdrIntensity]\n5 = 1.0\n").is_err());
+}
+
+#[test]
+fn costs_are_kept_per_card() {
+    assert_eq!(perf::costs_path(GPU), Path::new("perf/nvidia-geforce-rtx-3070.toml"));
+    assert_eq!(perf::costs_path("AMD Radeon RX 6800 XT (ROG)"), Path::new("perf/amd-radeon-rx-6800-xt-rog.toml"));
+}
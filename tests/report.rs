@@ -0,0 +1,41 @@
+use std::process::Command;
+use rdr2_gfx_selector::calibration::Calibration;
+use rdr2_gfx_selector::report::{self, ReportFormat};
+use rdr2_gfx_selector::settings::SettingType;
+use common::{config, remove_dir, temp_dir};
+
+mod common;
+
+#[test]
+fn out_of_range_files_are_reported_as_errors() {
+    let dir = temp_dir("report-range");
+    let path = dir.join("system.xml");
+    let content = config().to_xml().unwrap().replacen("<adapterIndex value=\"0\"/>", "<adapterIndex value=\"9\"/>", 1);
+    std::fs::write(&path, content).unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_rdr2-gfx-selector")).arg("report").arg(&path).output().unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("adapterIndex: 9 is not one of"), "{stderr}");
+    assert!(!stderr.contains("panicked"), "{stderr}");
+    remove_dir(&dir);
+}
+
+#[test]
+fn invalid_values_are_rendered_and_listed() {
+    let mut config = config();
+    for setting in config.settings_mut() {
+        match &mut setting.setting_type {
+            SettingType::Indexed(value, _) => *value = 9,
+            SettingType::Level(selected_index, ..) => *selected_index = 9,
+            _ => {}
+        }
+    }
+    for format in [ReportFormat::Markdown, ReportFormat::Text] {
+        let report = report::render(&config, format, true, &Calibration::builtin());
+        assert!(report.contains("9 (invalid)"), "{report}");
+        assert!(report.contains("Invalid values"), "{report}");
+        assert!(report.contains("adapterIndex"), "{report}");
+    }
+    let valid = report::render(&self::config(), ReportFormat::Markdown, false, &Calibration::builtin());
+    assert!(!valid.contains("Invalid values"), "{valid}");
+}
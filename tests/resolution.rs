@@ -0,0 +1,47 @@
+use rdr2_gfx_selector::{resolution, SystemConfig};
+
+#[test]
+fn modes_parse_by_name_or_size() {
+    assert_eq!(resolution::parse("1440p").unwrap(), (2560, 1440));
+    assert_eq!(resolution::parse("steam deck").unwrap(), (1280, 800));
+    assert_eq!(resolution::parse("3440x1440").unwrap(), (3440, 1440));
+    assert!(resolution::parse("wide").is_err());
+}
+
+#[test]
+fn modes_cycle_by_pixel_count() {
+    let mut current = (1280, 720);
+    for _ in 0..resolution::MODES.len() {
+        let next = resolution::next_mode(current.0, current.1, true);
+        if (next.width, next.height) == (1280, 720) {
+            break;
+        }
+        assert!(next.width * next.height >= current.0 * current.1);
+        current = (next.width, next.height);
+    }
+    assert_eq!(current, (3840, 2160));
+    let previous = resolution::next_mode(1280, 720, false);
+    assert_eq!((previous.width, previous.height), (3840, 2160));
+}
+
+#[test]
+fn aspect_ratios_use_common_names() {
+    assert_eq!(resolution::aspect_ratio(1920, 1080), "16:9");
+    assert_eq!(resolution::aspect_ratio(1280, 800), "16:10");
+    assert_eq!(resolution::aspect_ratio(3440, 1440), "21:9");
+    assert_eq!(resolution::height_for_width(3440, (2560, 1080), 8), 1448);
+}
+
+#[test]
+fn fullscreen_size_follows_the_sliders() {
+    let xml = SystemConfig::new().resolution_named("4K").video_card("Card").to_xml().unwrap();
+    assert!(xml.contains("<screenWidth value=\"3840\"/>"));
+    assert!(xml.contains("<screenHeight value=\"2160\"/>"));
+}
+
+#[test]
+fn rejected_resolution_changes_nothing() {
+    let mut settings = SystemConfig::new().settings().to_vec();
+    assert!(resolution::set_resolution(&mut settings, 1366, 0).is_err());
+    assert_eq!(rdr2_gfx_selector::vram::resolution(&settings), (1024, 768));
+}
@@ -0,0 +1,55 @@
+use rdr2_gfx_selector::calibration::Calibration;
+use rdr2_gfx_selector::settings::{self, Rule, SettingType};
+use rdr2_gfx_selector::{lint, rules, xml, Api, DlssQuality, Level, SystemConfig};
+
+fn broken(config: &SystemConfig) -> Vec<Rule> {
+    rules::check(config.settings(), config.defaults()).into_iter().map(|violation| violation.rule).collect()
+}
+
+#[test]
+fn defaults_break_no_rules() {
+    assert!(broken(&SystemConfig::new()).is_empty());
+}
+
+#[test]
+fn stacked_anti_aliasing_conflicts() {
+    let config = SystemConfig::new().msaa(4).taa(Level::High);
+    assert_eq!(broken(&config), [Rule::Conflicts("msaa", "taa")]);
+}
+
+#[test]
+fn changed_dependents_need_their_setting() {
+    let config = SystemConfig::new().hdr(false);
+    assert!(broken(&config).is_empty());
+    let mut config = config.toggle("treeTessellationEnabled", true);
+    config.set_value("ssdoSampleCount", "8").unwrap();
+    assert_eq!(broken(&config), [Rule::Requires("treeTessellationEnabled", "tessellation"), Rule::Requires("ssdoSampleCount", "ssao")]);
+}
+
+#[test]
+fn implied_values_are_applied() {
+    let mut config = SystemConfig::new().resolution_scale(2, 1).dlss(Some(DlssQuality::Quality));
+    assert_eq!(broken(&config), [Rule::Implies("dlssIndex", "scalingMode", "kSettingScale_Mode1o1")]);
+    assert_eq!(rules::apply_implied(&mut config).unwrap(), ["scalingMode=kSettingScale_Mode1o1"]);
+    assert!(broken(&config).is_empty());
+}
+
+#[test]
+fn lint_warns_about_broken_rules() {
+    let written = SystemConfig::new().msaa(2).fxaa(true).video_card("NVIDIA GeForce RTX 3070").to_xml().unwrap();
+    let findings = lint::lint_document(&xml::Document::parse(&written).unwrap(), 8192, &Calibration::builtin());
+    let finding = findings.iter().find(|finding| finding.code == "rule-conflicts").unwrap();
+    assert!(finding.message.contains("msaa and fxaaEnabled"));
+    assert!(!lint::has_errors(&findings));
+}
+
+#[test]
+fn switching_api_sets_its_companions() {
+    let vulkan = SystemConfig::new().api(Api::Vulkan);
+    assert!(matches!(vulkan.setting("asyncComputeEnabled").unwrap().setting_type, SettingType::OnOff(true)));
+    let dx12 = vulkan.api(Api::DirectX12);
+    assert!(matches!(dx12.setting("asyncComputeEnabled").unwrap().setting_type, SettingType::OnOff(false)));
+    assert!(broken(&dx12).is_empty());
+    let dx12 = dx12.toggle("transferQueuesEnabled", false);
+    assert_eq!(broken(&dx12), [Rule::OnlyWith("transferQueuesEnabled", "API", settings::VULKAN)]);
+}
@@ -0,0 +1,121 @@
+use rdr2_gfx_selector::settings::SettingType;
+use rdr2_gfx_selector::{share, xml, Api, Error, Level, SystemConfig};
+
+const ALPHABET: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+
+fn config() -> SystemConfig {
+    SystemConfig::new().api(Api::Vulkan).msaa(4).texture_quality(Level::Ultra).resolution(2560, 1440).refresh_rate(5994, 100)
+}
+
+fn decoded(code: &str) -> Result<SystemConfig, Error> {
+    let mut config = SystemConfig::new();
+    share::decode(code, config.settings_mut())?;
+    Ok(config)
+}
+
+fn invalid_message(code: &str) -> String {
+    match decoded(code) {
+        Err(Error::Validation(message)) => message,
+        Err(err) => panic!("expected a validation error, got {err}"),
+        Ok(_) => panic!("{code} was accepted"),
+    }
+}
+
+// Payload of a code without its checksum
+fn payload(code: &str) -> Vec<u8> {
+    let mut bytes = vec![];
+    let (mut buffer, mut buffered) = (0u32, 0);
+    for chr in code.chars().filter(|chr| *chr != '-') {
+        buffer = (buffer << 5) | ALPHABET.iter().position(|letter| *letter as char == chr).unwrap() as u32;
+        buffered += 5;
+        if buffered >= 8 {
+            buffered -= 8;
+            bytes.push((buffer >> buffered) as u8);
+        }
+    }
+    bytes.truncate(bytes.len() - 2);
+    bytes
+}
+
+// Code with a valid checksum for any payload, CRC-16/CCITT-FALSE like the real ones
+fn code_for(payload: &[u8]) -> String {
+    let mut crc: u16 = 0xFFFF;
+    for byte in payload {
+        crc ^= (*byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 { (crc << 1) ^ 0x1021 } else { crc << 1 };
+        }
+    }
+    let mut bytes = payload.to_vec();
+    bytes.extend_from_slice(&crc.to_be_bytes());
+    let mut text = String::new();
+    let (mut buffer, mut buffered) = (0u32, 0);
+    for byte in bytes {
+        buffer = (buffer << 8) | byte as u32;
+        buffered += 8;
+        while buffered >= 5 {
+            buffered -= 5;
+            text.push(ALPHABET[((buffer >> buffered) & 31) as usize] as char);
+        }
+    }
+    if buffered > 0 {
+        text.push(ALPHABET[((buffer << (5 - buffered)) & 31) as usize] as char);
+    }
+    text
+}
+
+#[test]
+fn codes_round_trip() {
+    let config = config();
+    let code = share::encode(config.settings()).unwrap();
+    let imported = decoded(&code).unwrap();
+    for (setting, expected) in imported.settings().iter().zip(config.settings()) {
+        assert_eq!(xml::setting_text(setting), xml::setting_text(expected), "{}", setting.tag);
+    }
+    // Typed by hand: lowercase, without dashes, O for 0
+    let typed = code.replace('-', "").to_lowercase().replace('0', "o");
+    assert_eq!(share::encode(decoded(&typed).unwrap().settings()).unwrap(), code);
+    assert_eq!(code_for(&payload(&code)), code.replace('-', ""));
+}
+
+#[test]
+fn mistyped_characters_fail_the_checksum() {
+    let code = share::encode(config().settings()).unwrap();
+    let position = code.len() / 2;
+    let original = code.as_bytes()[position];
+    let replacement = if original == b'A' { 'B' } else { 'A' };
+    let mistyped = format!("{}{replacement}{}", &code[..position], &code[position + 1..]);
+    assert!(invalid_message(&mistyped).contains("checksum"));
+    assert!(invalid_message(&format!("{code}U")).contains("'U' is not part of a code"));
+}
+
+#[test]
+fn other_versions_and_lengths_are_rejected() {
+    let mut bytes = payload(&share::encode(config().settings()).unwrap());
+    bytes[0] = share::CODE_VERSION + 1;
+    let message = invalid_message(&code_for(&bytes));
+    assert!(message.contains(&format!("layout {}", share::CODE_VERSION + 1)), "{message}");
+
+    bytes[0] = share::CODE_VERSION;
+    bytes.truncate(bytes.len() / 2);
+    assert!(invalid_message(&code_for(&bytes)).contains("too short for this catalog"));
+    assert!(invalid_message("0").contains("too short"));
+}
+
+#[test]
+fn values_that_dont_fit_are_not_encoded() {
+    let mut config = config();
+    if let SettingType::Indexed(value, _) = &mut config.setting_mut("adapterIndex").unwrap().setting_type {
+        *value = 9;
+    }
+    assert!(matches!(share::encode(config.settings()), Err(Error::Validation(_))));
+
+    let mut config = self::config();
+    if let SettingType::Slider(value, ..) = &mut config.setting_mut("screenWidthWindowed").unwrap().setting_type {
+        *value = 80_000;
+    }
+    let Err(Error::Validation(message)) = share::encode(config.settings()) else {
+        panic!("the width doesn't fit in 13 bits");
+    };
+    assert!(message.contains("doesn't fit"), "{message}");
+}
@@ -0,0 +1,92 @@
+use std::path::PathBuf;
+use std::time::{Duration, UNIX_EPOCH};
+use rdr2_gfx_selector::bench::{self, Benchmark};
+use rdr2_gfx_selector::sweep::{self, Run, Vary};
+use rdr2_gfx_selector::{xml, SystemConfig};
+use common::{config, remove_dir};
+
+mod common;
+
+const REPORT: &str = "\
+Frames Per Second (Higher is better) Min, Max, Avg
+Pass 0, 30.0, 110.0, 70.0
+Time in milliseconds(ms). (Lower is Better). Min, Max, Avg
+Pass 0, 9.0, 33.3, 14.3
+";
+
+// Settings directory with an empty Benchmarks directory
+fn settings_dir(name: &str) -> PathBuf {
+    let dir = common::temp_dir(&format!("sweep-{name}"));
+    std::fs::create_dir_all(dir.join(bench::BENCHMARKS_DIR)).unwrap();
+    dir
+}
+
+#[test]
+fn one_variant_per_value_after_the_base() {
+    let varies = [Vary::parse("msaa=0, 2,4").unwrap(), Vary::parse("hdr=true").unwrap()];
+    assert_eq!(varies[0].values, ["0", "2", "4"]);
+    let variants = sweep::variants(&config(), &varies).unwrap();
+    let labels: Vec<String> = variants.iter().map(|variant| variant.label()).collect();
+    assert_eq!(labels, ["base", "msaa=0", "msaa=2", "msaa=4", "hdr=true"]);
+    assert_eq!(xml::setting_text(variants[2].config.setting("msaa").unwrap()), "2");
+
+    assert!(Vary::parse("msaa").is_err());
+    assert!(Vary::parse("msaa=").is_err());
+    assert!(sweep::variants(&config(), &[Vary::parse("msaa=3").unwrap()]).is_err());
+}
+
+#[test]
+fn installing_backs_up_the_previous_file() {
+    let dir = settings_dir("install");
+    let variants = sweep::variants(&config(), &[Vary::parse("msaa=4").unwrap()]).unwrap();
+    assert_eq!(sweep::install(&variants[0], &dir).unwrap(), None);
+    let installed = dir.join(sweep::INSTALLED_FILE);
+    let written = std::fs::metadata(&installed).unwrap().modified().unwrap();
+
+    let backup = sweep::install(&variants[1], &dir).unwrap().unwrap();
+    assert_eq!(std::fs::metadata(&backup).unwrap().modified().unwrap(), written);
+    let restored = SystemConfig::read_file(&backup).unwrap();
+    assert_eq!(xml::setting_text(restored.setting("msaa").unwrap()), "0");
+    let current = SystemConfig::read_file(&installed).unwrap();
+    assert_eq!(xml::setting_text(current.setting("msaa").unwrap()), "4");
+    remove_dir(&dir);
+}
+
+#[test]
+fn waits_for_a_new_readable_report() {
+    let dir = settings_dir("wait");
+    let benchmarks = dir.join(bench::BENCHMARKS_DIR);
+    std::fs::write(benchmarks.join("old.txt"), REPORT).unwrap();
+    let seen = sweep::existing_reports(&benchmarks);
+    let interval = Duration::from_millis(10);
+    assert!(sweep::wait_for_report(&benchmarks, &seen, interval, Some(Duration::from_millis(50))).is_err());
+
+    let writer = {
+        let benchmarks = benchmarks.clone();
+        std::thread::spawn(move || {
+            std::fs::write(benchmarks.join("partial.txt"), "Frames Per Second").unwrap();
+            std::thread::sleep(Duration::from_millis(50));
+            std::fs::write(benchmarks.join("partial.txt"), REPORT).unwrap();
+        })
+    };
+    let benchmark = sweep::wait_for_report(&benchmarks, &seen, interval, Some(Duration::from_secs(10))).unwrap();
+    writer.join().unwrap();
+    assert_eq!(benchmark.path.file_name().unwrap(), "partial.txt");
+    remove_dir(&dir);
+}
+
+#[test]
+fn runs_are_written_as_csv() {
+    let variants = sweep::variants(&config(), &[Vary::parse("msaa=2").unwrap()]).unwrap();
+    let benchmark = Benchmark {
+        path: PathBuf::from("Benchmarks/run, 1.txt"),
+        modified: UNIX_EPOCH,
+        passes: bench::parse(REPORT).unwrap(),
+    };
+    let runs: Vec<Run> = variants.into_iter().map(|variant| Run { variant, benchmark: benchmark.clone() }).collect();
+    assert_eq!(sweep::to_csv(&runs), "\
+setting,value,report,time,avg_fps,min_fps,avg_frame_ms
+base,,\"run, 1.txt\",1970-01-01 00:00,70.00,30.00,14.300
+msaa,2,\"run, 1.txt\",1970-01-01 00:00,70.00,30.00,14.300
+");
+}
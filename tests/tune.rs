@@ -0,0 +1,175 @@
+use rdr2_gfx_selector::settings::{self, Setting, SettingType};
+use rdr2_gfx_selector::tune::{self, TuneOptions};
+use rdr2_gfx_selector::{rules, vram, xml, Error, Level, SystemConfig};
+use common::{command, config, home_with, remove_dir, GPU};
+
+mod common;
+
+fn level_of(settings: &[Setting], tag: &str) -> usize {
+    let index = settings::get_setting_index_by_tag(settings, tag).unwrap();
+    position(&settings[index].setting_type)
+}
+
+fn position(setting_type: &SettingType) -> usize {
+    match setting_type {
+        SettingType::Level(selected_index, _, _) => *selected_index,
+        SettingType::Multiplier(value, _) => *value,
+        _ => panic!("not a tunable setting"),
+    }
+}
+
+fn tuned(budget: usize) -> Vec<Setting> {
+    let mut settings = SystemConfig::new().resolution(2560, 1440).settings().to_vec();
+    tune::tune(&mut settings, &TuneOptions::new(budget)).unwrap();
+    settings
+}
+
+#[test]
+fn same_inputs_give_same_settings() {
+    let first = share_code(&tuned(3000));
+    let second = share_code(&tuned(3000));
+    assert_eq!(first, second);
+}
+
+fn share_code(settings: &[Setting]) -> String {
+    rdr2_gfx_selector::share::encode(settings).unwrap()
+}
+
+#[test]
+fn stays_within_budget() {
+    for budget in [2500, 3000, 4000, 6000] {
+        let settings = tuned(budget);
+        assert!(vram::estimate_vram(&settings) <= budget as f64, "over {budget} MB");
+    }
+}
+
+#[test]
+fn large_budget_maxes_everything_but_conflicts() {
+    let settings = tuned(20_000);
+    for setting in &settings {
+        let Some(values) = tune::tunable_values(setting) else {
+            continue;
+        };
+        // TAA is worth more than MSAA, they don't go together
+        let expected = if setting.tag == "msaa" { values.first() } else { values.last() };
+        assert_eq!(position(&setting.setting_type), position(expected.unwrap()), "{} is not where it should be", setting.tag);
+    }
+}
+
+#[test]
+fn tuned_configs_break_no_rules() {
+    for ((width, height), budget) in [((2560, 1440), 8192), ((3840, 2160), 12000), ((1920, 1080), 4000), ((3840, 2160), 20_000)] {
+        let config = SystemConfig::new().resolution(width, height);
+        let mut settings = config.settings().to_vec();
+        tune::tune(&mut settings, &TuneOptions::new(budget)).unwrap();
+        let violations = rules::check(&settings, config.defaults());
+        assert!(violations.is_empty(), "{width}x{height} at {budget} MB: {:?}", violations.iter().map(|violation| &violation.message).collect::<Vec<_>>());
+
+        for point in tune::frontier(config.settings(), &TuneOptions::new(budget)).unwrap() {
+            assert!(rules::check(&point.settings, config.defaults()).is_empty(), "frontier point at {:.0} MB", point.vram);
+        }
+    }
+
+    // A post-process AA left on by the player keeps MSAA off
+    let config = SystemConfig::new().resolution(2560, 1440).fxaa(true);
+    let mut settings = config.settings().to_vec();
+    tune::tune(&mut settings, &TuneOptions::new(20_000).lock("taa")).unwrap();
+    assert_eq!(level_of(&settings, "msaa"), 0);
+    assert!(rules::check(&settings, config.defaults()).is_empty());
+}
+
+#[test]
+fn lowering_switches_off_what_conflicts_with_the_kept_setting() {
+    let mut settings = SystemConfig::new().resolution(2560, 1440).taa(Level::High).msaa(8).settings().to_vec();
+    let msaa = settings::get_setting_index_by_tag(&settings, "msaa").unwrap();
+    let budget = vram::estimate_vram(&settings) as usize - 1;
+    let lowered = tune::lower_to_fit(&mut settings, msaa, &TuneOptions::new(budget)).unwrap();
+    assert_eq!(level_of(&settings, "taa"), 0);
+    assert_eq!(level_of(&settings, "msaa"), 8);
+    assert!(lowered.iter().any(|entry| settings[entry.index].tag == "taa"));
+}
+
+#[test]
+fn tight_budget_prefers_textures() {
+    let settings = tuned(3000);
+    assert!(level_of(&settings, "textureQuality") >= level_of(&settings, "reflectionQuality"));
+}
+
+#[test]
+fn locked_settings_keep_their_value() {
+    let config = SystemConfig::new().resolution(2560, 1440);
+    let mut settings = config.settings().to_vec();
+    let before = level_of(&settings, "textureQuality");
+    let options = TuneOptions::new(20_000).lock("textureQuality");
+    tune::tune(&mut settings, &options).unwrap();
+    assert_eq!(level_of(&settings, "textureQuality"), before);
+}
+
+#[test]
+fn budget_below_lowest_settings_is_an_error() {
+    let mut settings = settings::get_settings();
+    let result = tune::tune(&mut settings, &TuneOptions::new(100));
+    assert!(matches!(result, Err(Error::Validation(_))));
+}
+
+#[test]
+fn frontier_gets_richer_and_costlier() {
+    let settings = SystemConfig::new().resolution(2560, 1440).settings().to_vec();
+    let points = tune::frontier(&settings, &TuneOptions::new(4000)).unwrap();
+    assert!(points.len() > 1);
+    for pair in points.windows(2) {
+        assert!(pair[0].score < pair[1].score);
+        assert!(pair[0].vram < pair[1].vram);
+    }
+    assert!(points.last().unwrap().vram <= 4000.0);
+}
+
+#[test]
+fn frontier_ends_at_the_tuned_config() {
+    let settings = SystemConfig::new().resolution(2560, 1440).settings().to_vec();
+    let points = tune::frontier(&settings, &TuneOptions::new(3000)).unwrap();
+    let richest = &points.last().unwrap().settings;
+    assert_eq!(share_code(richest), share_code(&tuned(3000)));
+}
+
+#[test]
+fn lowering_fits_without_touching_kept_and_locked() {
+    let mut settings = SystemConfig::new()
+        .level("reflectionQuality", Level::Ultra)
+        .level("textureQuality", Level::Ultra)
+        .level("volumetricsQuality", Level::Ultra)
+        .settings()
+        .to_vec();
+    let texture = settings::get_setting_index_by_tag(&settings, "textureQuality").unwrap();
+    let options = TuneOptions::new(3000).lock("volumetricsQuality");
+    let lowered = tune::lower_to_fit(&mut settings, texture, &options).unwrap();
+    assert!(!lowered.is_empty());
+    assert!(vram::estimate_vram(&settings) <= 3000.0);
+    assert_eq!(level_of(&settings, "textureQuality"), 3);
+    assert_eq!(level_of(&settings, "volumetricsQuality"), 3);
+}
+
+#[test]
+fn lowering_changes_nothing_when_it_cant_fit() {
+    let mut settings = SystemConfig::new().level("textureQuality", Level::Ultra).settings().to_vec();
+    let texture = settings::get_setting_index_by_tag(&settings, "textureQuality").unwrap();
+    let before = share_code(&settings);
+    assert!(tune::lower_to_fit(&mut settings, texture, &TuneOptions::new(1700)).is_err());
+    assert_eq!(share_code(&settings), before);
+}
+
+#[test]
+fn without_base_the_installed_file_is_tuned() {
+    let home = home_with("tune-installed", &config().hdr(true));
+    let output = command(&home)
+        .args(["tune", "--vram", "8192", "--resolution", "2560x1440", "--output", "out.xml"])
+        .output().unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let tuned = SystemConfig::read_file(&home.join("out.xml")).unwrap();
+    assert_eq!(tuned.video_card_name(), Some(GPU));
+    assert_eq!(xml::setting_text(tuned.setting("hdr").unwrap()), "true");
+
+    let output = command(&home).args(["frontier", "--vram", "8192"]).output().unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    remove_dir(&home);
+}
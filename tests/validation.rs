@@ -0,0 +1,83 @@
+use rdr2_gfx_selector::settings::{self, Setting, SettingType};
+use rdr2_gfx_selector::xml::Document;
+use rdr2_gfx_selector::{share, validation, ScreenType, SystemConfig};
+
+fn setting(settings: &[Setting], tag: &str) -> Setting {
+    settings[settings::get_setting_index_by_tag(settings, tag).unwrap()].clone()
+}
+
+#[test]
+fn typed_numbers_are_checked_before_they_are_set() {
+    let settings = settings::get_settings();
+    let mut width = setting(&settings, "screenWidthWindowed");
+    validation::set_number(&mut width, 2560).unwrap();
+    assert!(matches!(width.setting_type, SettingType::Slider(2560, _, _)));
+    validation::set_number(&mut width, 1366).unwrap();
+    assert!(matches!(width.setting_type, SettingType::Slider(1366, _, _)));
+    assert!(validation::set_number(&mut width, 0).is_err());
+    assert!(matches!(width.setting_type, SettingType::Slider(1366, _, _)));
+
+    let mut msaa = setting(&settings, "msaa");
+    assert!(validation::set_number(&mut msaa, 3).is_err());
+    assert!(validation::set_number(&mut msaa, 16).is_err());
+    validation::set_number(&mut msaa, 4).unwrap();
+
+    let mut vsync = setting(&settings, "vSync");
+    assert!(validation::set_number(&mut vsync, 3).is_err());
+    assert!(validation::set_number(&mut setting(&settings, "textureQuality"), 1).is_err());
+}
+
+#[test]
+fn refresh_rates_are_typed_as_hertz() {
+    let settings = settings::get_settings();
+    let mut rate = setting(&settings, "refreshRateNumerator");
+    validation::set_typed(&mut rate, "59.94").unwrap();
+    assert!(matches!(rate.setting_type, SettingType::RefreshRate(5994, 100)));
+    validation::set_typed(&mut rate, "144").unwrap();
+    assert!(matches!(rate.setting_type, SettingType::RefreshRate(144, 1)));
+    assert!(validation::set_typed(&mut rate, "1000").is_err());
+    assert!(validation::set_typed(&mut rate, "60/0").is_err());
+    assert!(validation::set_typed(&mut rate, "6o").is_err());
+    assert!(matches!(rate.setting_type, SettingType::RefreshRate(144, 1)));
+
+    let mut screen = setting(&settings, "windowed");
+    assert!(validation::set_number(&mut screen, 3).is_err());
+    validation::set_number(&mut screen, 0).unwrap();
+}
+
+#[test]
+fn display_settings_survive_the_file_and_share_codes() {
+    let config = SystemConfig::new()
+        .screen_type(ScreenType::Fullscreen)
+        .adapter(1)
+        .output(2)
+        .refresh_rate(60000, 1001)
+        .video_card("NVIDIA GeForce RTX 3070");
+    let xml = config.to_xml().unwrap();
+    assert!(xml.contains("<windowed value=\"0\"/>"));
+    assert!(xml.contains("<refreshRateNumerator value=\"60000\"/>"));
+    assert!(xml.contains("<refreshRateDenominator value=\"1001\"/>"));
+
+    let read = SystemConfig::from_document(&Document::parse(&xml).unwrap()).unwrap();
+    assert_eq!(read.to_xml().unwrap(), xml);
+
+    let mut decoded = settings::get_settings();
+    share::decode(&share::encode(config.settings()).unwrap(), &mut decoded).unwrap();
+    assert!(matches!(setting(&decoded, "refreshRateNumerator").setting_type, SettingType::RefreshRate(60000, 1001)));
+    assert!(matches!(setting(&decoded, "outputIndex").setting_type, SettingType::Indexed(2, _)));
+}
+
+#[test]
+fn hdr_numbers_have_ranges_and_follow_hdr() {
+    let config = SystemConfig::new().toggle("hdr", false);
+    let gamma = config.setting("gamma").unwrap();
+    assert_eq!(settings::depends_on("gamma"), Some("hdr"));
+    assert!(!settings::is_active(config.settings(), gamma));
+    assert!(settings::is_active(SystemConfig::new().settings(), gamma));
+
+    let mut peak = config.setting("hdrPeakBrightness").unwrap().clone();
+    validation::set_number(&mut peak, 1234).unwrap();
+    assert!(validation::set_number(&mut peak, 20_000).is_err());
+    assert!(peak.setting_type.increase());
+    assert!(matches!(peak.setting_type, SettingType::Range(1284, _, _, _)));
+}
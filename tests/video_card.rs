@@ -0,0 +1,117 @@
+use std::path::Path;
+use rdr2_gfx_selector::video_card::{self, CardCheck, VideoCardDescription};
+use rdr2_gfx_selector::export::{self, Format};
+use rdr2_gfx_selector::{settings, share, xml, Error, SystemConfig};
+use common::{command, config, home_with, remove_dir, temp_dir, GPU};
+
+mod common;
+
+const OTHER_GPU: &str = "AMD Radeon RX 6800 XT";
+
+fn write_for(card_name: &str, path: &Path) {
+    xml::write_file(path, &settings::get_settings(), &xml::default_elements(), card_name).unwrap();
+}
+
+fn card(name: Option<&str>, current: Option<&str>) -> VideoCardDescription {
+    VideoCardDescription { name: name.map(String::from), current: current.map(String::from), known: vec![] }
+}
+
+#[test]
+fn names_are_checked_against_the_installed_file() {
+    assert!(matches!(card(Some(GPU), Some(GPU)).check(), CardCheck::Matching));
+    assert!(matches!(card(Some(GPU), None).check(), CardCheck::NoCurrentFile));
+    assert!(matches!(card(None, Some(GPU)).check(), CardCheck::Missing));
+    let CardCheck::Mismatch(current) = card(Some(OTHER_GPU), Some(GPU)).check() else {
+        panic!("the names differ");
+    };
+    assert_eq!(current, GPU);
+}
+
+#[test]
+fn writing_needs_a_name() {
+    assert_eq!(card(Some(GPU), None).require_name().unwrap(), GPU);
+    assert!(matches!(card(None, Some(GPU)).require_name(), Err(Error::Validation(_))));
+
+    let mut typed = card(None, None);
+    typed.set_typed("  NVIDIA GeForce RTX 4070 \n");
+    assert_eq!(typed.name.as_deref(), Some("NVIDIA GeForce RTX 4070"));
+    typed.set_typed("   ");
+    assert_eq!(typed.name, None);
+}
+
+#[test]
+fn names_are_collected_from_backups_and_profiles() {
+    let dir = temp_dir("video-card-load");
+    let profiles = dir.join("profiles");
+    std::fs::create_dir_all(&profiles).unwrap();
+    write_for(GPU, &dir.join("system.xml"));
+    write_for(OTHER_GPU, &dir.join("system.xml.1.bak"));
+    write_for("Intel Arc A770", &profiles.join("arc.xml"));
+    std::fs::write(profiles.join("broken.xml"), "<Rage").unwrap();
+
+    let mut card = VideoCardDescription::load_from(Some(&dir), &profiles).unwrap();
+    assert_eq!(card.name.as_deref(), Some(GPU));
+    assert_eq!(card.current.as_deref(), Some(GPU));
+    assert_eq!(card.known, [GPU, OTHER_GPU, "Intel Arc A770"]);
+    card.cycle(true);
+    assert!(matches!(card.check(), CardCheck::Mismatch(_)));
+    card.cycle(false);
+    assert!(matches!(card.check(), CardCheck::Matching));
+
+    // Nothing installed yet isn't an error
+    let empty = VideoCardDescription::load_from(Some(&dir.join("missing")), &dir.join("missing")).unwrap();
+    assert_eq!(empty.current, None);
+    assert_eq!(video_card::read_video_card_name(&dir.join("missing.xml")).unwrap(), None);
+    remove_dir(&dir);
+}
+
+#[test]
+fn broken_installed_files_report_where() {
+    let dir = temp_dir("video-card-broken");
+    std::fs::write(dir.join("system.xml"), "<rage__fwuiSystemSettingsCollection>\n  <version value=\"37\">\n</rage__fwuiSystemSettingsCollection>\n").unwrap();
+    let Err(err) = VideoCardDescription::load_from(Some(&dir), &dir.join("profiles")) else {
+        panic!("the file is broken");
+    };
+    assert!(matches!(err, Error::Parse { line: 3, column: 1, .. }), "{err}");
+    assert_eq!(err.to_string(), "Parse error at 3:1: expected </version>, found </rage__fwuiSystemSettingsCollection>");
+
+    std::fs::write(dir.join("system.xml"), b"<a>\n\xff</a>").unwrap();
+    let err = video_card::read_video_card_name(&dir.join("system.xml")).unwrap_err();
+    assert!(matches!(err, Error::Parse { line: 2, column: 1, .. }), "{err}");
+    remove_dir(&dir);
+}
+
+// Refused with a hint at --force, then written with it
+#[test]
+fn writing_another_card_needs_force() {
+    let home = home_with("card-force", &config());
+    let other = config().video_card(OTHER_GPU);
+    other.write_file(&home.join("other.xml")).unwrap();
+    std::fs::write(home.join("other.toml"), export::export(&other, Format::Toml)).unwrap();
+    let code = share::encode(other.settings()).unwrap();
+    let runs: [&[&str]; 3] = [
+        &["import", "other.toml", "--output", "out.xml"],
+        &["set", "msaa=4", "--base", "other.xml", "--output", "out.xml"],
+        &["import-code", &code, "--base", "other.xml", "--output", "out.xml"],
+    ];
+    for args in runs {
+        let output = command(&home).args(args).output().unwrap();
+        assert_eq!(output.status.code(), Some(1), "{args:?}");
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        assert!(stderr.contains(&format!("installed system.xml has '{GPU}'")) && stderr.contains("--force"), "{stderr}");
+        assert!(!home.join("out.xml").exists());
+
+        let output = command(&home).args(args).arg("--force").output().unwrap();
+        assert!(output.status.success(), "{args:?}: {}", String::from_utf8_lossy(&output.stderr));
+        let written = SystemConfig::read_file(&home.join("out.xml")).unwrap();
+        assert_eq!(written.video_card_name(), Some(OTHER_GPU));
+        std::fs::remove_file(home.join("out.xml")).unwrap();
+    }
+
+    std::fs::write(home.join("same.toml"), export::export(&config(), Format::Toml)).unwrap();
+    let output = command(&home).args(["import", "same.toml", "--output", "out.xml"]).output().unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let output = command(&home).args(["set", "msaa=4", "--output", "out.xml"]).output().unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    remove_dir(&home);
+}
@@ -0,0 +1,43 @@
+use rdr2_gfx_selector::{vram, DlssQuality, FsrQuality, Level, SystemConfig};
+
+fn estimate(config: SystemConfig) -> f64 {
+    vram::estimate_vram(config.settings())
+}
+
+#[test]
+fn render_target_costs_grow_with_resolution() {
+    let msaa_at = |width, height| estimate(SystemConfig::new().resolution(width, height).msaa(8))
+        - estimate(SystemConfig::new().resolution(width, height));
+    assert!(msaa_at(1024, 768).abs() < 1e-9);
+    assert!(msaa_at(3840, 2160) > 2.0 * msaa_at(1920, 1080));
+}
+
+#[test]
+fn flat_costs_dont_depend_on_resolution() {
+    let texture_at = |width, height| estimate(SystemConfig::new().resolution(width, height).texture_quality(Level::Ultra))
+        - estimate(SystemConfig::new().resolution(width, height));
+    assert!((texture_at(1920, 1080) - texture_at(3840, 2160)).abs() < 1e-6);
+}
+
+#[test]
+fn render_resolution_is_the_screen_without_scaling() {
+    let config = SystemConfig::new().resolution(2560, 1440);
+    assert_eq!(vram::render_resolution(config.settings()), (2560, 1440));
+}
+
+#[test]
+fn upscalers_lower_the_render_resolution() {
+    let native = SystemConfig::new().resolution(3840, 2160).msaa(4);
+    let dlss = native.clone().dlss(Some(DlssQuality::Performance));
+    assert_eq!(vram::render_resolution(dlss.settings()), (1920, 1080));
+    assert!(estimate(dlss) < estimate(native.clone()));
+
+    let fsr = native.clone().fsr(Some(FsrQuality::Performance));
+    assert_eq!(vram::render_resolution(fsr.settings()), (1920, 1080));
+    // DLSS replaces the resolution scale the same way the game greys it out
+    let scaled = native.resolution_scale(2, 1);
+    assert_eq!(vram::render_resolution(scaled.settings()), (7680, 4320));
+    let both = scaled.dlss(Some(DlssQuality::Dlaa));
+    assert_eq!(vram::render_resolution(both.settings()), (3840, 2160));
+    assert!(SystemConfig::new().resolution_scale(3, 7).check_settings().is_err());
+}
@@ -0,0 +1,82 @@
+use std::path::Path;
+use std::time::Duration;
+use rdr2_gfx_selector::watch::{self, Change, Watcher};
+use rdr2_gfx_selector::{Level, SystemConfig};
+use common::{config, remove_dir, temp_dir};
+
+mod common;
+
+fn profile() -> SystemConfig {
+    config().texture_quality(Level::Ultra).msaa(4)
+}
+
+#[test]
+fn changes_are_compared_with_the_profile() {
+    let dir = temp_dir("watch-check");
+    let path = dir.join("system.xml");
+    let profile = profile();
+    assert!(matches!(watch::check(&profile, &path), Change::Removed));
+    profile.write_file(&path).unwrap();
+    assert!(matches!(watch::check(&profile, &path), Change::Unchanged));
+
+    profile.clone().msaa(2).write_file(&path).unwrap();
+    let Change::Changed { differences, reset, .. } = watch::check(&profile, &path) else {
+        panic!("msaa changed");
+    };
+    assert_eq!(differences.len(), 1);
+    assert_eq!((differences[0].tag.as_str(), differences[0].expected.as_str(), differences[0].found.as_str()), ("msaa", "4", "2"));
+    assert!(!reset);
+
+    // Everything we set back at its default is a reset, so is another card name
+    config().write_file(&path).unwrap();
+    assert!(matches!(watch::check(&profile, &path), Change::Changed { reset: true, .. }));
+    profile.clone().video_card("NVIDIA GeForce RTX 4070").write_file(&path).unwrap();
+    assert!(matches!(watch::check(&profile, &path), Change::Changed { reset: true, .. }));
+
+    std::fs::write(&path, "<Rage").unwrap();
+    assert!(matches!(watch::check(&profile, &path), Change::Unreadable(_)));
+    remove_dir(&dir);
+}
+
+#[test]
+fn reapplying_keeps_the_new_card_name() {
+    let dir = temp_dir("watch-reapply");
+    let path = dir.join("system.xml");
+    let mut profile = profile();
+    let reset = SystemConfig::new().video_card("NVIDIA GeForce RTX 4070");
+    reset.write_file(&path).unwrap();
+
+    let backup = watch::reapply(&mut profile, Some(&reset), &path, false).unwrap();
+    assert!(backup.is_some_and(|backup| backup.exists()));
+    assert_eq!(profile.video_card_name(), Some("NVIDIA GeForce RTX 4070"));
+    // Writing it back isn't a change of its own
+    assert!(matches!(watch::check(&profile, &path), Change::Unchanged));
+    remove_dir(&dir);
+}
+
+fn assert_sees_writes(mut watcher: Watcher, path: &Path) {
+    assert!(!watcher.wait(Some(Duration::from_millis(100))).unwrap());
+    let writer = {
+        let path = path.to_path_buf();
+        std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(100));
+            profile().msaa(8).write_file(&path).unwrap();
+        })
+    };
+    assert!(watcher.wait(Some(Duration::from_secs(10))).unwrap());
+    writer.join().unwrap();
+}
+
+#[test]
+fn watchers_wake_up_on_writes() {
+    let dir = temp_dir("watch-wait");
+    let path = dir.join("system.xml");
+    profile().write_file(&path).unwrap();
+    let watcher = Watcher::new(&path, Duration::from_millis(10));
+    if cfg!(target_os = "linux") {
+        assert!(watcher.uses_inotify());
+    }
+    assert_sees_writes(watcher, &path);
+    assert_sees_writes(Watcher::polling(&path, Duration::from_millis(10)), &path);
+    remove_dir(&dir);
+}
@@ -0,0 +1,45 @@
+use rdr2_gfx_selector::calibration::Calibration;
+use rdr2_gfx_selector::{lint, xml, SystemConfig};
+use common::{config, remove_dir, temp_dir};
+
+mod common;
+
+#[test]
+fn read_only_is_cleared_before_the_next_write() {
+    let dir = temp_dir("write-protect");
+    let path = dir.join("system.xml");
+    config().msaa(2).write_file_read_only(&path).unwrap();
+    assert!(xml::is_read_only(&path).unwrap());
+
+    config().msaa(4).write_file(&path).unwrap();
+    assert!(!xml::is_read_only(&path).unwrap());
+    let written = SystemConfig::read_file(&path).unwrap();
+    assert_eq!(xml::setting_text(written.setting("msaa").unwrap()), "4");
+    // Only the file itself is left behind
+    let names: Vec<_> = std::fs::read_dir(&dir).unwrap().map(|entry| entry.unwrap().file_name()).collect();
+    assert_eq!(names, ["system.xml"]);
+    remove_dir(&dir);
+}
+
+#[test]
+fn backups_of_protected_files_are_writable() {
+    let dir = temp_dir("write-backup");
+    let path = dir.join("system.xml");
+    config().write_file_read_only(&path).unwrap();
+    let backup = xml::backup_file(&path).unwrap().unwrap();
+    assert!(!xml::is_read_only(&backup).unwrap());
+    assert!(xml::is_read_only(&path).unwrap());
+    remove_dir(&dir);
+}
+
+#[test]
+fn lint_reports_read_only_files() {
+    let dir = temp_dir("write-lint");
+    let path = dir.join("system.xml");
+    config().write_file(&path).unwrap();
+    let read_only = |path| lint::lint_file(path, 8192, &Calibration::builtin()).unwrap().iter().any(|finding| finding.code == "read-only");
+    assert!(!read_only(&path));
+    xml::set_read_only(&path, true).unwrap();
+    assert!(read_only(&path));
+    remove_dir(&dir);
+}
@@ -0,0 +1,65 @@
+use rdr2_gfx_selector::settings::{self, SettingType};
+use rdr2_gfx_selector::settings::XMLSection::Graphics;
+use rdr2_gfx_selector::xml::{self, Document, Value};
+use rdr2_gfx_selector::Error;
+
+fn parse_error(content: &str) -> (usize, usize, String) {
+    match Document::parse(content) {
+        Err(Error::Parse { line, column, message }) => (line, column, message),
+        Err(err) => panic!("expected a parse error, got {err}"),
+        Ok(_) => panic!("expected a parse error"),
+    }
+}
+
+#[test]
+fn elements_remember_their_position() {
+    let content = "<?xml version=\"1.0\"?>\n<root>\n  <graphics>\n    <msaa value=\"4\" />\n    <tessellation>kSettingLevel_High</tessellation>\n  </graphics>\n</root>\n";
+    let document = Document::parse(content).unwrap();
+    assert_eq!(document.root, "root");
+    let msaa = document.find(Some(Graphics), "msaa").unwrap();
+    assert_eq!((msaa.line, msaa.column), (4, 5));
+    assert_eq!(msaa.value, Value::Attribute("4".into()));
+    let tessellation = document.find(Some(Graphics), "tessellation").unwrap();
+    assert_eq!((tessellation.line, tessellation.column), (5, 5));
+    assert_eq!(tessellation.value, Value::Text("kSettingLevel_High".into()));
+}
+
+#[test]
+fn errors_point_at_the_offending_markup() {
+    assert_eq!(
+        parse_error("<root>\n  <graphics>\n  </video>\n</root>"),
+        (3, 3, "expected </graphics>, found </video>".into())
+    );
+    assert_eq!(parse_error("<root>\n  <msaa value=\"4 />\n</root>"), (2, 3, "unterminated attribute 'value' in <msaa>".into()));
+    assert_eq!(parse_error("<root>\n  <msaa value=4 />\n</root>"), (2, 3, "unquoted attribute 'value' in <msaa>".into()));
+    assert_eq!(parse_error("<root>\n  <graphics>\n</root>").2, "expected </graphics>, found </root>");
+    assert_eq!(parse_error("<root>\n  <graphics>"), (2, 3, "<graphics> is never closed".into()));
+    assert_eq!(parse_error("<root/>\n<root/>"), (2, 1, "more than one root element".into()));
+    assert_eq!(parse_error("<root/>\n</root>"), (2, 1, "unexpected closing tag </root>".into()));
+    assert_eq!(parse_error("<root>\n  <msaa"), (2, 3, "missing '>'".into()));
+    assert_eq!(parse_error("  \n"), (1, 1, "no root element".into()));
+}
+
+#[test]
+fn written_files_parse_back() {
+    let settings = settings::get_settings();
+    let content = xml::to_xml(&settings, "NVIDIA GeForce RTX 3070 & <co>").unwrap();
+    let document = Document::parse(&content).unwrap();
+    assert_eq!(document.root, xml::ROOT_TAG);
+    assert_eq!(document.version(), Some(xml::VERSION));
+    assert_eq!(document.video_card(), Some("NVIDIA GeForce RTX 3070 & <co>"));
+}
+
+#[test]
+fn invalid_settings_are_not_serialized() {
+    let mut settings = settings::get_settings();
+    let index = settings::get_setting_index_by_tag(&settings, "textureQuality").unwrap();
+    if let SettingType::Level(selected_index, ..) = &mut settings[index].setting_type {
+        *selected_index = 9;
+    }
+    match xml::to_xml(&settings, "Card") {
+        Err(Error::Validation(message)) => assert!(message.contains("textureQuality"), "{message}"),
+        other => panic!("expected a validation error, got {other:?}"),
+    }
+    assert!(xml::to_xml_with_defaults(&settings, &xml::default_elements(), "Card").is_err());
+}